@@ -0,0 +1,90 @@
+// Benchmark of the read buffer size when hashing a large file, run with `cargo bench --bench hash`
+// Hashes one temporary file with read loops of several buffer sizes, 4 KB being the size hash_file used before,
+// then with hash_file itself; the file is read once beforehand, so the runs measure the read calls rather than the disk
+// XXH3 hashes faster than the reads go, so the cost of the read calls shows most there
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+use dupfinder::{hash_file, HashAlgo};
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+// Size of the file hashed, large enough for the number of read calls to matter
+const FILE_SIZE: usize = 512 * 1024 * 1024;
+
+// Buffer sizes compared, from the old one to well past the current one
+const BUFFER_SIZES: [usize; 5] = [4 * 1024, 16 * 1024, 64 * 1024, 256 * 1024, 1024 * 1024];
+
+// Runs of each variant, the fastest of which is reported
+const RUNS: usize = 3;
+
+// Function to read a file `buffer_size` bytes at a time, like hash_file does, feeding every chunk to `update`
+fn read_with_buffer(path: &str, buffer_size: usize, mut update: impl FnMut(&[u8])) {
+    let mut file = File::open(path).unwrap();
+    let mut buffer = vec![0; buffer_size]; // One buffer, whatever the file size
+    loop {
+        let bytes_read = file.read(&mut buffer).unwrap();
+        if bytes_read == 0 {
+            break;
+        }
+        update(&buffer[..bytes_read]);
+    }
+}
+
+// Function to hash a file with `algo` reading `buffer_size` bytes at a time, giving the same digest as hash_file
+fn hash_with_buffer(path: &str, algo: HashAlgo, buffer_size: usize) -> String {
+    match algo {
+        HashAlgo::XxHash => {
+            let mut hasher = Xxh3::new();
+            read_with_buffer(path, buffer_size, |chunk| hasher.update(chunk));
+            format!("{:032x}", hasher.digest128())
+        }
+        _ => {
+            let mut hasher = Sha256::new(); // The only other algorithm benchmarked
+            read_with_buffer(path, buffer_size, |chunk| hasher.update(chunk));
+            format!("{:x}", hasher.finalize())
+        }
+    }
+}
+
+// Function to time the fastest of RUNS runs of `hash`, checking each gives the expected digest
+fn fastest(expected: &str, mut hash: impl FnMut() -> String) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let started = Instant::now();
+            assert_eq!(hash(), expected);
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// Function to format a throughput like "1234 MB/s"
+fn throughput(time: Duration) -> String {
+    format!("{:.0} MB/s", FILE_SIZE as f64 / 1_000_000.0 / time.as_secs_f64())
+}
+
+fn main() {
+    // Fill the file with varying bytes, so nothing can shortcut the hashing
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let block: Vec<u8> = (0..1024 * 1024).map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8).collect();
+    for _ in 0..FILE_SIZE / block.len() {
+        file.write_all(&block).unwrap();
+    }
+    file.flush().unwrap();
+    let path = file.path().display().to_string();
+    let cancel = AtomicBool::new(false);
+
+    for algo in [HashAlgo::XxHash, HashAlgo::Sha256] {
+        let expected = hash_with_buffer(&path, algo, 64 * 1024); // Also brings the file into the page cache
+        println!("Hashing {} MB with {}, fastest of {} runs", FILE_SIZE / (1024 * 1024), algo.label(), RUNS);
+        for buffer_size in BUFFER_SIZES {
+            let time = fastest(&expected, || hash_with_buffer(&path, algo, buffer_size));
+            println!("{:>5} KB buffer: {:>8.1} ms, {}", buffer_size / 1024, time.as_secs_f64() * 1000.0, throughput(time));
+        }
+        let time = fastest(&expected, || hash_file(&path, algo, &cancel).unwrap());
+        println!("    hash_file: {:>8.1} ms, {}", time.as_secs_f64() * 1000.0, throughput(time));
+    }
+}
@@ -0,0 +1,122 @@
+// Benchmark of how hash workers publish their progress, run with `cargo bench --bench progress`
+// Workers go through many tiny files, where updating the shared progress costs as much as the hashing itself:
+// once with every file locking the shared fraction and current file, like the scan did before, and once with
+// each worker batching its count into relaxed atomics, like hash_in_parallel does now
+// The thread count defaults to the number of cores and can be set with BENCH_THREADS; contention only shows with
+// several cores
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use xxhash_rust::xxh3::xxh3_64;
+
+// Files hashed per run, and the bytes of each, small enough for the progress updates to dominate
+const FILES: usize = 2_000_000;
+const FILE_SIZE: usize = 64;
+
+// Same batching as hash_in_parallel: at most every PROGRESS_BATCH files or PROGRESS_INTERVAL
+const PROGRESS_BATCH: usize = 256;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+
+// Runs of each variant, the fastest of which is reported
+const RUNS: usize = 5;
+
+// Paths and contents of the files hashed, and a way of hashing them all
+type Files = [(String, Vec<u8>)];
+type Variant = fn(&Files, &Progress) -> u64;
+
+// Progress shared by the workers and the UI
+#[derive(Default)]
+struct Progress {
+    processed: AtomicUsize,
+    fraction: Mutex<f32>, // How the scan used to publish its progress
+    current_file: Mutex<String>,
+}
+
+// Function to stand in for hashing one file
+fn hash(file: &[u8], index: usize) -> u64 {
+    xxh3_64(file) ^ index as u64
+}
+
+// Every file updates the shared fraction and current file under their locks
+fn per_file(files: &Files, progress: &Progress) -> u64 {
+    let total = files.len();
+    files
+        .par_iter()
+        .enumerate()
+        .map(|(index, (path, file))| {
+            progress.current_file.lock().unwrap().clone_from(path);
+            let hash = hash(file, index);
+            let processed = progress.processed.fetch_add(1, Ordering::Relaxed) + 1;
+            *progress.fraction.lock().unwrap() = processed as f32 / total as f32;
+            hash
+        })
+        .reduce(|| 0, |a, b| a ^ b)
+}
+
+// Work of one worker not published yet, like HashBatch
+#[derive(Default)]
+struct Batch {
+    hashes: u64,
+    unreported: usize,
+    reported_at: Option<Instant>,
+}
+
+// Each worker counts its files locally and adds them to a relaxed atomic now and then, never waiting on a lock
+fn batched(files: &Files, progress: &Progress) -> u64 {
+    let merged = files
+        .par_iter()
+        .enumerate()
+        .fold(Batch::default, |mut batch, (index, (path, file))| {
+            let due = batch.unreported >= PROGRESS_BATCH || batch.reported_at.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL);
+            if due {
+                if let Ok(mut current_file) = progress.current_file.try_lock() {
+                    current_file.clone_from(path);
+                }
+            }
+            batch.hashes ^= hash(file, index);
+            batch.unreported += 1;
+            if due {
+                progress.processed.fetch_add(batch.unreported, Ordering::Relaxed);
+                batch.unreported = 0;
+                batch.reported_at = Some(Instant::now());
+            }
+            batch
+        })
+        .reduce(Batch::default, |mut merged, batch| {
+            merged.hashes ^= batch.hashes;
+            merged.unreported += batch.unreported;
+            merged
+        });
+    progress.processed.fetch_add(merged.unreported, Ordering::Relaxed); // Whatever the workers hadn't published yet
+    merged.hashes
+}
+
+// Function to time the fastest of RUNS runs of `variant`, checking the hashes and that the count ends exact
+fn fastest(files: &Files, expected: u64, variant: Variant) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let progress = Progress::default();
+            let started = Instant::now();
+            assert_eq!(variant(files, &progress), expected);
+            let time = started.elapsed();
+            assert_eq!(progress.processed.load(Ordering::Relaxed), files.len()); // Settles at 1.0 either way
+            time
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let threads = std::env::var("BENCH_THREADS").ok().and_then(|threads| threads.parse().ok()).unwrap_or(0);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    let files: Vec<(String, Vec<u8>)> = (0..FILES).map(|i| (format!("/data/file{}.bin", i), vec![i as u8; FILE_SIZE])).collect();
+    let expected = files.iter().enumerate().fold(0, |hashes, (index, (_, file))| hashes ^ hash(file, index));
+
+    println!("{} files of {} bytes on {} threads, fastest of {} runs", FILES, FILE_SIZE, pool.current_num_threads(), RUNS);
+    for (name, variant) in [("locked per file", per_file as Variant), ("batched atomics", batched)] {
+        let time = pool.install(|| fastest(&files, expected, variant));
+        println!("{:>15}: {:>7.1} ms, {:.0} ns per file", name, time.as_secs_f64() * 1000.0, time.as_nanos() as f64 / FILES as f64);
+    }
+}
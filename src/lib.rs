@@ -0,0 +1,2399 @@
+// Scanning engine of DupFinder: walking directories, hashing files and grouping the duplicates
+// The GUI and the command-line mode in main.rs are thin front ends over DupScanner
+
+// Import necessary modules and crates
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use regex::{Regex, RegexBuilder};
+use sha2::{Digest, Sha256};
+use walkdir::{DirEntry, WalkDir};
+use xxhash_rust::xxh3::Xxh3;
+use rayon::prelude::*;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use clap::ValueEnum;
+
+// Hashing algorithms available for comparing file contents
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, ValueEnum)]
+pub enum HashAlgo {
+    Sha256, // Cryptographically strong, but the slowest option
+    Blake3, // Strong and much faster than SHA-256
+    #[value(name = "xxhash")]
+    XxHash, // Non-cryptographic XXH3 (128-bit), fastest option
+}
+
+impl HashAlgo {
+    pub const ALL: [HashAlgo; 3] = [HashAlgo::Sha256, HashAlgo::Blake3, HashAlgo::XxHash]; // All algorithms, in display order
+
+    // Human-readable name of the algorithm
+    pub fn label(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "SHA-256",
+            HashAlgo::Blake3 => "BLAKE3",
+            HashAlgo::XxHash => "xxHash (XXH3)",
+        }
+    }
+}
+
+// Number of leading bytes hashed in the cheap first phase of a scan
+const PARTIAL_HASH_SIZE: u64 = 4096;
+
+// Size of the buffer used to read files, large enough to keep the number of read calls low on big files
+// Each worker thread only ever holds one buffer, so memory stays bounded whatever the file size
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+// Number of chunks read between checks of the cancellation flag
+const CANCEL_CHECK_INTERVAL: usize = 16;
+
+// Function to read data in chunks, feeding every chunk to `update`
+// Returns an `Interrupted` error as soon as `cancel` is set
+fn read_chunks(reader: &mut impl Read, cancel: &AtomicBool, mut update: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buffer = vec![0; READ_BUFFER_SIZE]; // Buffer to hold file data
+    let mut chunks = 0;
+
+    // Read the data in chunks and pass them on, failing on any read error so a partial hash is never returned
+    loop {
+        let bytes_read = match reader.read(&mut buffer) {
+            Ok(0) => break, // Break the loop if no more bytes are read
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue, // Interrupted by a signal, retry the read
+            Err(e) => return Err(e),
+        };
+        update(&buffer[..bytes_read]); // Hand the read bytes to the hasher
+
+        // Check every few chunks whether the scan was cancelled
+        chunks += 1;
+        if chunks % CANCEL_CHECK_INTERVAL == 0 && cancel.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "scan cancelled"));
+        }
+    }
+    Ok(())
+}
+
+// Function to hash everything read from `reader` using the selected algorithm
+fn hash_reader(mut reader: impl Read, algo: HashAlgo, cancel: &AtomicBool) -> io::Result<String> {
+    // Return the final hash in hexadecimal format
+    let hash = match algo {
+        HashAlgo::Sha256 => {
+            let mut hasher = Sha256::new(); // Create a new SHA-256 hasher
+            read_chunks(&mut reader, cancel, |chunk| hasher.update(chunk))?;
+            format!("{:x}", hasher.finalize())
+        }
+        HashAlgo::Blake3 => {
+            let mut hasher = blake3::Hasher::new(); // Create a new BLAKE3 hasher
+            read_chunks(&mut reader, cancel, |chunk| {
+                hasher.update(chunk);
+            })?;
+            hasher.finalize().to_hex().to_string()
+        }
+        HashAlgo::XxHash => {
+            let mut hasher = Xxh3::new(); // Create a new XXH3 hasher
+            read_chunks(&mut reader, cancel, |chunk| hasher.update(chunk))?;
+            format!("{:032x}", hasher.digest128())
+        }
+    };
+    Ok(hash)
+}
+
+// Attempts at reading a file before a transient error is reported, and the wait before the first retry, doubling each time
+const READ_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(100);
+
+// Function to tell whether an I/O error may go away by itself, like a network share dropping the connection for a moment
+// Missing files, denied permissions and the like fail the same way every time, so they're never retried
+fn is_transient(e: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    if matches!(
+        e.kind(),
+        TimedOut | WouldBlock | ConnectionReset | ConnectionAborted | NotConnected | BrokenPipe | NetworkDown | NetworkUnreachable
+            | HostUnreachable | StaleNetworkFileHandle | ResourceBusy
+    ) {
+        return true;
+    }
+    // Generic I/O errors of network file systems, and files briefly locked by another program on Windows
+    #[cfg(unix)]
+    const TRANSIENT_CODES: &[i32] = &[5]; // EIO
+    #[cfg(windows)]
+    const TRANSIENT_CODES: &[i32] = &[32, 33, 59, 64, 121]; // Sharing and lock violations, network errors, semaphore timeout
+    #[cfg(not(any(unix, windows)))]
+    const TRANSIENT_CODES: &[i32] = &[];
+    e.raw_os_error().is_some_and(|code| TRANSIENT_CODES.contains(&code))
+}
+
+// Function to run a read of a file again after a transient error, up to READ_ATTEMPTS times with a growing delay
+// Gives up right away once the scan is cancelled, which also ends reads with an `Interrupted` error
+fn with_retries<T>(path: &str, cancel: &AtomicBool, mut read: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1.. {
+        match read() {
+            Err(e) if attempt < READ_ATTEMPTS && is_transient(&e) && !cancel.load(Ordering::Relaxed) => {
+                log::warn!("Failed to read {} ({}), retrying in {} ms", path, e, delay.as_millis());
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+// Function to hash a file (or a file inside a zip archive) using the selected algorithm, giving up early if `cancel` is set
+// Transient read errors are retried a few times before they're reported
+pub fn hash_file(path: &str, algo: HashAlgo, cancel: &AtomicBool) -> io::Result<String> {
+    with_retries(path, cancel, || with_reader(path, |reader, _| hash_reader(reader, algo, cancel)))
+}
+
+// Function to hash the first `limit` bytes of a file during a scan, counting them into `progress` as they're read
+// With a limit of PARTIAL_HASH_SIZE, files no larger than that get the same hash as the full one
+fn hash_file_tracked(path: &str, algo: HashAlgo, limit: u64, cancel: &AtomicBool, progress: &ScanProgress, counted: &Cell<u64>) -> io::Result<String> {
+    with_retries(path, cancel, || {
+        progress.bytes_done.fetch_sub(counted.replace(0), Ordering::Relaxed); // A retry starts over, so the progress goes back
+        with_reader(path, |reader, _| hash_reader(ProgressReader { inner: reader.take(limit), progress, counted }, algo, cancel))
+    })
+}
+
+// Extensions of the files normalized by the text normalization mode, provided their content is UTF-8 text
+const TEXT_EXTENSIONS: [&str; 36] = [
+    "txt", "md", "csv", "tsv", "log", "ini", "cfg", "conf", "json", "xml", "yml", "yaml", "toml", "html", "htm", "css", "js", "ts",
+    "py", "rs", "c", "h", "cpp", "hpp", "java", "cs", "go", "rb", "php", "pl", "sh", "bash", "ps1", "bat", "cmd", "sql",
+];
+
+// Largest text file normalized, as it's read into memory whole; bigger ones are compared byte for byte
+const NORMALIZE_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+// Function to check whether a file is a candidate for text normalization by its extension and size
+fn is_text_candidate(file: &FileEntry) -> bool {
+    let extension = Path::new(&file.path).extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    file.size <= NORMALIZE_MAX_SIZE && extension.is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// Function to read a file as normalized text, or None when it turns out to be binary: not UTF-8, or holding NUL bytes
+fn read_normalized(path: &str, normalization: TextNormalization, cancel: &AtomicBool) -> io::Result<Option<String>> {
+    let bytes = with_retries(path, cancel, || {
+        let mut bytes = Vec::new();
+        with_reader(path, |reader, _| reader.read_to_end(&mut bytes)).map(|_| bytes)
+    })?;
+    Ok(String::from_utf8(bytes).ok().filter(|text| !text.contains('\0')).map(|text| normalization.apply(&text)))
+}
+
+// Prefix marking the hashes of normalized text, which are grouped apart from the raw hashing
+const NORMALIZED_PREFIX: &str = "text:";
+
+// Function to hash a text file in normalized form, prefixed with NORMALIZED_PREFIX; binary files get their raw hash
+fn hash_normalized(path: &str, algo: HashAlgo, normalization: TextNormalization, cancel: &AtomicBool) -> io::Result<String> {
+    match read_normalized(path, normalization, cancel)? {
+        Some(text) => Ok(format!("{}{}", NORMALIZED_PREFIX, hash_reader(text.as_bytes(), algo, cancel)?)),
+        None => hash_file(path, algo, cancel),
+    }
+}
+
+// Separator between the path of an archive and the name of a file inside it, as in `photos.zip::2019/beach.jpg`
+pub const ARCHIVE_SEPARATOR: &str = "::";
+
+// Function to check whether a path has the extension of a zip archive
+fn is_zip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+// Function to split the path of a file inside a zip archive into the archive and the entry name
+// Returns None for regular files
+pub fn split_archive_path(path: &str) -> Option<(&str, &str)> {
+    path.match_indices(ARCHIVE_SEPARATOR)
+        .map(|(i, _)| (&path[..i], &path[i + ARCHIVE_SEPARATOR.len()..]))
+        .find(|(archive, _)| is_zip(Path::new(archive)))
+}
+
+// Function to get the file on disk holding a path: the archive for files inside one, the path itself otherwise
+pub fn containing_file(path: &str) -> &str {
+    split_archive_path(path).map_or(path, |(archive, _)| archive)
+}
+
+// Longest path the legacy Windows file APIs accept, counting the terminating null (MAX_PATH)
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+// Function to turn a path too long for MAX_PATH into the extended-length form `\\?\C:\...`, or `\\?\UNC\server\share\...`
+// for network shares, which Windows opens whatever its length; shorter and already extended paths are returned as they are
+#[cfg(windows)]
+pub fn extended_path(path: &Path) -> Cow<'_, Path> {
+    let text = path.as_os_str();
+    if text.len() < MAX_PATH || text.to_string_lossy().starts_with(r"\\?\") {
+        return Cow::Borrowed(path);
+    }
+    // Extended paths are taken literally, so `..` and forward slashes have to be resolved first
+    let Some(full) = std::path::absolute(path).ok().and_then(|full| full.to_str().map(str::to_string)) else {
+        return Cow::Borrowed(path);
+    };
+    match full.strip_prefix(r"\\") {
+        Some(share) => Cow::Owned(PathBuf::from(format!(r"\\?\UNC\{}", share))),
+        None => Cow::Owned(PathBuf::from(format!(r"\\?\{}", full))),
+    }
+}
+
+// Paths have no length limit worth working around elsewhere
+#[cfg(not(windows))]
+pub fn extended_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
+// Function to open a file, or a file inside a zip archive, and pass it to `read` along with its size
+// The archive is opened again for every entry, which is simple but makes archives slower to scan than loose files
+pub fn with_reader<T>(path: &str, read: impl FnOnce(&mut dyn Read, u64) -> io::Result<T>) -> io::Result<T> {
+    let Some((archive, name)) = split_archive_path(path) else {
+        let mut file = File::open(extended_path(Path::new(path)))?; // Attempt to open the file, however deep it is
+        let size = file.metadata()?.len();
+        return read(&mut file, size);
+    };
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(File::open(extended_path(Path::new(archive)))?)).map_err(io::Error::other)?;
+    let mut entry = archive.by_name(name).map_err(io::Error::other)?; // Decompressed while it's read
+    let size = entry.size();
+    read(&mut entry, size)
+}
+
+// Function to list the files inside a zip archive with their uncompressed sizes
+fn archive_entries(path: &Path) -> io::Result<Vec<(String, u64)>> {
+    let mut archive = zip::ZipArchive::new(io::BufReader::new(File::open(extended_path(path))?)).map_err(io::Error::other)?;
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i).map_err(io::Error::other)?;
+        if entry.is_file() {
+            entries.push((entry.name().map_err(io::Error::other)?.into_owned(), entry.size()));
+        }
+    }
+    Ok(entries)
+}
+
+// Steps of a scan, in the order they run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanPhase {
+    #[default]
+    Walking, // Listing the files of the directories
+    Grouping, // Setting apart the files whose size no other file has, or grouping by similar size
+    PartialHash, // Hashing the first block of each candidate
+    FullHash, // Hashing the candidates whose first blocks matched, and normalized text files
+    Comparing, // Computing the perceptual hashes of images
+}
+
+impl ScanPhase {
+    // Name shown next to the progress
+    pub fn label(self) -> &'static str {
+        match self {
+            ScanPhase::Walking => "Discovering files",
+            ScanPhase::Grouping => "Grouping by size",
+            ScanPhase::PartialHash => "Hashing first blocks",
+            ScanPhase::FullHash => "Hashing whole files",
+            ScanPhase::Comparing => "Comparing images",
+        }
+    }
+}
+
+// Rough cost of finding or opening a file, in bytes that could have been read meanwhile, so phases handling
+// many small files or only a block of each still weigh something next to phases reading whole files
+const FILE_COST: u64 = 64 * 1024;
+
+// The current phase and the slice of the overall progress it covers
+#[derive(Clone, Copy)]
+struct PhaseProgress {
+    phase: ScanPhase,
+    base: f64, // Overall fraction reached when the phase started
+    share: f64, // Part of the overall progress the phase spans, so it ends at `base + share`
+    from: u64, // Work done when the phase started, in the units of `done_and_total`
+}
+
+impl Default for PhaseProgress {
+    fn default() -> Self {
+        Self { phase: ScanPhase::Walking, base: 0.0, share: 0.0, from: 0 } // The walk's share is only known once it's over
+    }
+}
+
+// Progress counters shared between the scan workers and the UI
+#[derive(Default)]
+pub struct ScanProgress {
+    pub processed: AtomicUsize, // Files processed so far, whether hashing succeeded or not
+    pub total: AtomicUsize, // Total number of files to process
+    pub files_found: AtomicUsize, // Files passing the filters, whether they needed hashing or not
+    pub discovered: AtomicBool, // Whether the directory walk is over, so hashing can start
+    pub bytes_read: AtomicU64, // Bytes read from disk for hashing, excluding cached hashes
+    pub bytes_total: AtomicU64, // Bytes the hashing has to go through, cached files included; 0 while unknown
+    pub bytes_done: AtomicU64, // Bytes gone through so far, advancing while large files are read
+    pub current_file: Mutex<String>, // Path of the file currently being hashed
+    pub panics: AtomicUsize, // Files whose processing panicked, skipped and reported as errors instead of ending the scan
+    phase: Mutex<PhaseProgress>, // Phase of the scan, weighing the counters into one fraction
+}
+
+impl ScanProgress {
+    // Phase the scan is in
+    pub fn phase(&self) -> ScanPhase {
+        self.phase.lock().unwrap().phase
+    }
+
+    // Move on to the next phase, covering `share` of the overall progress from `base`
+    // The phases run one after the other, so the counters only have to be offset by what was done before
+    fn start_phase(&self, phase: ScanPhase, base: f64, share: f64) {
+        let (from, _) = self.done_and_total();
+        *self.phase.lock().unwrap() = PhaseProgress { phase, base, share, from };
+    }
+
+    // Overall fraction the current phase ends at, where the next one starts
+    fn phase_end(&self) -> f64 {
+        let phase = self.phase.lock().unwrap();
+        phase.base + phase.share
+    }
+
+    // Work done and to do, in bytes when the sizes are known so large files advance the progress smoothly,
+    // and in files otherwise
+    fn done_and_total(&self) -> (u64, u64) {
+        let bytes_total = self.bytes_total.load(Ordering::Relaxed);
+        if bytes_total > 0 {
+            (self.bytes_done.load(Ordering::Relaxed).min(bytes_total), bytes_total)
+        } else {
+            let total = self.total.load(Ordering::Relaxed) as u64;
+            ((self.processed.load(Ordering::Relaxed) as u64).min(total), total)
+        }
+    }
+
+    // Fraction of the scan completed, between 0.0 and 1.0, weighing each phase by its estimated work
+    // A phase whose amount of work only becomes known once it starts takes up what's left, so the fraction
+    // never goes back when the totals grow
+    pub fn fraction(&self) -> f32 {
+        let phase = *self.phase.lock().unwrap();
+        let (done, total) = self.done_and_total();
+        let within = if total > phase.from { done.saturating_sub(phase.from) as f64 / (total - phase.from) as f64 } else { 0.0 };
+        (phase.base + phase.share * within.min(1.0)).min(1.0) as f32
+    }
+
+    // Estimated time left, extrapolated from the rate achieved since the scan started
+    pub fn eta(&self, elapsed: Duration) -> Option<Duration> {
+        let (done, total) = self.done_and_total();
+        if done == 0 || done >= total {
+            return None; // No rate to extrapolate from yet
+        }
+        Some(elapsed.mul_f64((total - done) as f64 / done as f64))
+    }
+
+    // Run the hashing of a file expected to go through `expected` bytes; `work` gets the count of the bytes its
+    // `ProgressReader` read, and whatever it didn't read, like cached hashes or failed reads, counts once it's done
+    fn track<T>(&self, expected: u64, work: impl FnOnce(&Cell<u64>) -> T) -> T {
+        let tracked = Tracked { progress: self, expected, counted: Cell::new(0) }; // Settles up even if `work` panics
+        work(&tracked.counted)
+    }
+
+    // Copy of the counters as they are right now, for consumers rendering their own progress
+    pub fn snapshot(&self) -> ProgressUpdate {
+        ProgressUpdate {
+            processed: self.processed.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+            files_found: self.files_found.load(Ordering::Relaxed),
+            discovered: self.discovered.load(Ordering::Relaxed),
+            bytes_done: self.bytes_done.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total.load(Ordering::Relaxed),
+            fraction: self.fraction(),
+            phase: self.phase(),
+            current_file: self.current_file.lock().unwrap().clone(),
+        }
+    }
+}
+
+// Bytes of one file being tracked by `ScanProgress::track`, the rest of which count once it's dropped
+struct Tracked<'a> {
+    progress: &'a ScanProgress,
+    expected: u64, // Bytes the file was expected to go through
+    counted: Cell<u64>, // Bytes counted as they were read
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        self.progress.bytes_done.fetch_add(self.expected.saturating_sub(self.counted.get()), Ordering::Relaxed);
+    }
+}
+
+// The progress of a scan at one moment, handed to the `on_progress` callback
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgressUpdate {
+    pub processed: usize, // Files processed so far
+    pub total: usize, // Total number of files to process, growing while the directories are walked
+    pub files_found: usize, // Files passing the filters
+    pub discovered: bool, // Whether the directory walk is over
+    pub bytes_done: u64, // Bytes gone through so far
+    pub bytes_total: u64, // Bytes to go through, 0 while unknown
+    pub fraction: f32, // Fraction of the scan completed, between 0.0 and 1.0
+    pub phase: ScanPhase, // Phase the scan is in
+    pub current_file: String, // Path being walked or hashed, empty when there's none
+}
+
+// Reader adding the bytes it passes on to the scan's counters as they're read, so progress advances within a file
+struct ProgressReader<'a, R> {
+    inner: R, // Reader of the file being hashed
+    progress: &'a ScanProgress, // Counters of the scan
+    counted: &'a Cell<u64>, // Bytes counted for this file so far
+}
+
+impl<R: Read> Read for ProgressReader<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buffer)?;
+        self.counted.set(self.counted.get() + n as u64);
+        self.progress.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+        self.progress.bytes_done.fetch_add(n as u64, Ordering::Relaxed); // One update per chunk of READ_BUFFER_SIZE
+        Ok(n)
+    }
+}
+
+// Pause switch shared between the UI and the scan workers
+#[derive(Default)]
+pub struct PauseFlag {
+    paused: Mutex<bool>, // Whether the workers should wait
+    resumed: Condvar, // Wakes the waiting workers up when the pause ends
+}
+
+impl PauseFlag {
+    // Pause or resume the scan, waking up any waiting worker
+    pub fn set(&self, paused: bool) {
+        *self.paused.lock().unwrap() = paused;
+        self.resumed.notify_all();
+    }
+
+    // Whether the scan is paused
+    pub fn is_paused(&self) -> bool {
+        *self.paused.lock().unwrap()
+    }
+
+    // Block the calling worker while the scan is paused, without using any CPU
+    // Returns early once the scan is cancelled, which always comes with a wake-up
+    pub fn wait(&self, cancel: &AtomicBool) {
+        let mut paused = self.paused.lock().unwrap();
+        while *paused && !cancel.load(Ordering::Relaxed) {
+            paused = self.resumed.wait(paused).unwrap();
+        }
+    }
+}
+
+// Where a scan is in its life, shared by a front end and the thread running the scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ScanState {
+    Idle = 0, // No scan running, or the last one was stopped
+    Scanning = 1, // The scan is running
+    Stopping = 2, // The scan was stopped, its thread is winding down and publishes nothing
+    Done = 3, // The scan completed and handed its results over
+}
+
+// A `ScanState` both sides update atomically, so a scan can't both complete and be stopped
+#[derive(Default)]
+pub struct SharedScanState(AtomicU8);
+
+impl SharedScanState {
+    // Create the state of a scan about to start, or of none
+    pub fn new(state: ScanState) -> Self {
+        SharedScanState(AtomicU8::new(state as u8))
+    }
+
+    // State the scan is in right now
+    pub fn get(&self) -> ScanState {
+        match self.0.load(Ordering::Acquire) {
+            1 => ScanState::Scanning,
+            2 => ScanState::Stopping,
+            3 => ScanState::Done,
+            _ => ScanState::Idle,
+        }
+    }
+
+    // Put the scan in a state whatever it was in, for the side that alone decides it then
+    pub fn set(&self, state: ScanState) {
+        self.0.store(state as u8, Ordering::Release);
+    }
+
+    // Move from one state to another, unless the other side moved it first
+    pub fn transition(&self, from: ScanState, to: ScanState) -> bool {
+        self.0.compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire).is_ok()
+    }
+
+    // Whether the scan is running and not stopped yet
+    pub fn is_scanning(&self) -> bool {
+        self.get() == ScanState::Scanning
+    }
+
+    // Whether the scan's thread may still be running, stopped or not, so nothing it shares should be used yet
+    pub fn is_busy(&self) -> bool {
+        matches!(self.get(), ScanState::Scanning | ScanState::Stopping)
+    }
+
+    // Called by the thread running the scan once it returns: whether it may still publish its outcome
+    pub fn finish(&self) -> bool {
+        if self.transition(ScanState::Scanning, ScanState::Done) {
+            return true;
+        }
+        self.transition(ScanState::Stopping, ScanState::Idle); // Stopped meanwhile, the outcome is dropped
+        false
+    }
+
+    // Called by the front end, from whatever state: a running scan winds down to idle, a finished one is idle right away
+    pub fn stop(&self) {
+        if !self.transition(ScanState::Scanning, ScanState::Stopping) {
+            self.set(ScanState::Idle); // The thread already finished, or there was nothing to stop
+        }
+    }
+}
+
+// A file found during a scan
+#[derive(Clone, Debug)]
+pub struct FileEntry {
+    pub path: String, // Path of the file
+    pub size: u64, // Size in bytes when the file was found
+    pub modified: Option<SystemTime>, // Last modification time, if the platform reports it
+    pub root: usize, // Index of the scanned directory the file was found in
+}
+
+// A group of files sharing the same content
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    pub hash: String, // Content hash of the files, with a suffix when a group had to be split
+    pub content: String, // Key of the content the files share, the same for the groups split from one by file name
+    pub size: u64, // Size of each file in bytes (the smallest file's size for similar images)
+    pub files: Vec<FileEntry>, // Files in the group
+    pub distance: Option<u32>, // Largest perceptual-hash distance within the group, None for exact duplicates
+    pub approximate: bool, // Grouped by similar size only, the contents were never compared
+    pub normalized: bool, // Holds text files that only match once normalized, so their bytes may differ
+}
+
+impl DuplicateGroup {
+    // Create a group of exact duplicates
+    pub fn new(hash: String, size: u64, files: Vec<FileEntry>) -> Self {
+        Self { content: hash.clone(), hash, size, files, distance: None, approximate: false, normalized: false }
+    }
+
+    // Space that would be freed by keeping only one file of the group
+    pub fn wasted_space(&self) -> u64 {
+        self.size * self.files.len().saturating_sub(1) as u64
+    }
+
+    // Alphabetically first path of the group
+    pub fn first_path(&self) -> Option<&str> {
+        self.files.iter().map(|file| file.path.as_str()).min()
+    }
+
+    // Whether the group holds zero-byte files, which all share one hash but are rarely real duplicates
+    pub fn is_empty_files(&self) -> bool {
+        self.size == 0 && self.distance.is_none()
+    }
+}
+
+// Syntax of the file name pattern
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PatternKind {
+    Glob, // Shell-style wildcards matching the whole name, like *.bak
+    Regex, // Regular expression matching anywhere in the name, like IMG_\d+
+}
+
+impl PatternKind {
+    // Human-readable name of the syntax
+    pub fn label(self) -> &'static str {
+        match self {
+            PatternKind::Glob => "Glob",
+            PatternKind::Regex => "Regex",
+        }
+    }
+}
+
+// Whether file names that only differ by case are different files on this platform's usual filesystems,
+// the default of every name comparison
+pub const CASE_SENSITIVE_NAMES: bool = cfg!(not(any(windows, target_os = "macos")));
+
+// Function to compile a file name pattern, returning None when it's empty
+// Globs support `*` and `?`; both kinds only ignore case when `case_sensitive` is off
+pub fn parse_name_pattern(pattern: &str, kind: PatternKind, case_sensitive: bool) -> Result<Option<Regex>, regex::Error> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Ok(None); // No pattern, every name matches
+    }
+    let regex = match kind {
+        PatternKind::Glob => {
+            let mut regex = String::from("^");
+            for c in pattern.chars() {
+                match c {
+                    '*' => regex.push_str(".*"),
+                    '?' => regex.push('.'),
+                    _ => regex.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            regex.push('$');
+            RegexBuilder::new(&regex).case_insensitive(!case_sensitive).build()?
+        }
+        PatternKind::Regex => RegexBuilder::new(pattern).case_insensitive(!case_sensitive).build()?,
+    };
+    Ok(Some(regex))
+}
+
+// Function to check whether a walked entry is hidden (dot-prefixed, or flagged hidden/system on Windows)
+fn is_hidden(entry: &DirEntry) -> bool {
+    if entry.file_name().to_string_lossy().starts_with('.') {
+        return true; // Unix-style hidden entry
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if let Ok(metadata) = entry.metadata() {
+            return metadata.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0;
+        }
+    }
+
+    false
+}
+
+// Function to check whether a file has been protected on purpose: it's read-only, or a system file on Windows
+// The Windows archive attribute only says that a file changed since the last backup and is set on almost every file,
+// so it doesn't count as protection
+fn is_protected(metadata: &fs::Metadata) -> bool {
+    if metadata.permissions().readonly() {
+        return true; // Nobody may write to it
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+        if metadata.file_attributes() & FILE_ATTRIBUTE_SYSTEM != 0 {
+            return true;
+        }
+    }
+
+    false
+}
+
+// How symbolic links are treated while walking the scan roots
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, ValueEnum)]
+pub enum SymlinkPolicy {
+    #[default]
+    Skip, // Ignore symbolic links entirely (the default, so nothing is scanned twice)
+    Follow, // Follow links into directories and files; loops are reported as errors
+    #[value(name = "files")]
+    ListAsFiles, // Treat links to files as files, without entering linked directories
+}
+
+impl SymlinkPolicy {
+    pub const ALL: [SymlinkPolicy; 3] = [SymlinkPolicy::Skip, SymlinkPolicy::Follow, SymlinkPolicy::ListAsFiles]; // All policies
+
+    // Human-readable name of the policy
+    pub fn label(self) -> &'static str {
+        match self {
+            SymlinkPolicy::Skip => "Skip symlinks",
+            SymlinkPolicy::Follow => "Follow symlinks",
+            SymlinkPolicy::ListAsFiles => "List symlinks as files",
+        }
+    }
+}
+
+// How Windows reparse points other than links are treated while walking, like OneDrive placeholders or
+// deduplicated files; junctions and symbolic links count as links and follow the SymlinkPolicy instead
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, ValueEnum)]
+pub enum ReparsePolicy {
+    #[default]
+    Skip, // Leave them out, reading a placeholder would download the whole file
+    Include, // Scan them like ordinary files and folders
+}
+
+impl ReparsePolicy {
+    pub const ALL: [ReparsePolicy; 2] = [ReparsePolicy::Skip, ReparsePolicy::Include]; // All policies
+
+    // Human-readable name of the policy
+    pub fn label(self) -> &'static str {
+        match self {
+            ReparsePolicy::Skip => "Skip reparse points",
+            ReparsePolicy::Include => "Include reparse points",
+        }
+    }
+}
+
+// Function to check whether a walked entry is a Windows reparse point that isn't a link
+// Links, junctions included, are told apart by the walk itself and never counted here
+fn is_reparse_point(entry: &DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+        if !entry.path_is_symlink() {
+            if let Ok(metadata) = entry.metadata() {
+                return metadata.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0;
+            }
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = entry; // Only Windows has them
+
+    false
+}
+
+// How text files are normalized before hashing, so copies differing only in formatting count as duplicates
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, ValueEnum)]
+pub enum TextNormalization {
+    LineEndings, // CRLF and CR line endings count as LF
+    #[value(name = "whitespace")]
+    TrailingWhitespace, // Line endings too, plus whitespace at the end of lines and of the file is dropped
+}
+
+impl TextNormalization {
+    pub const ALL: [TextNormalization; 2] = [TextNormalization::LineEndings, TextNormalization::TrailingWhitespace]; // All modes
+
+    // Human-readable name of the mode
+    pub fn label(self) -> &'static str {
+        match self {
+            TextNormalization::LineEndings => "Line endings",
+            TextNormalization::TrailingWhitespace => "Line endings and trailing whitespace",
+        }
+    }
+
+    // Function to bring a text into the normalized form that gets hashed
+    pub fn apply(self, text: &str) -> String {
+        let unified = text.replace("\r\n", "\n").replace('\r', "\n");
+        match self {
+            TextNormalization::LineEndings => unified,
+            TextNormalization::TrailingWhitespace => {
+                let lines: Vec<&str> = unified.split('\n').map(str::trim_end).collect();
+                lines.join("\n").trim_end().to_string()
+            }
+        }
+    }
+}
+
+// Filters deciding which files are considered during a scan
+#[derive(Clone, Debug, Default)]
+pub struct ScanFilter {
+    pub include_extensions: Vec<String>, // Only scan these extensions (lowercase); empty means all
+    pub exclude_extensions: Vec<String>, // Never scan these extensions (lowercase), takes precedence over includes
+    pub skip_hidden: bool, // Skip hidden files and folders
+    pub skip_protected: bool, // Skip read-only files, and system files on Windows, so they're never suggested for deletion
+    pub skip_recent: Option<Duration>, // Skip files modified less than this long ago, like work in progress, if set
+    pub pruned_dirs: Vec<String>, // Directory names whose subtrees are never walked
+    pub excluded_dirs: Vec<PathBuf>, // Directory paths whose subtrees are never walked
+    pub max_depth: Option<usize>, // Deepest level walked, 1 meaning only the files directly inside each directory; unlimited if None
+    pub min_size: u64, // Smallest file size to scan in bytes (inclusive)
+    pub max_size: Option<u64>, // Largest file size to scan in bytes (inclusive), if limited
+    pub symlinks: SymlinkPolicy, // How symbolic links are treated
+    pub reparse_points: ReparsePolicy, // How Windows reparse points that aren't links are treated
+    pub name_pattern: Option<Regex>, // Only scan files whose name matches, if set
+    pub archives: bool, // Also scan the files inside zip archives
+    pub file_list: Option<Vec<PathBuf>>, // Scan exactly these files instead of walking the directories, if set
+}
+
+impl ScanFilter {
+    // Check whether the walk should enter or yield an entry; the scan roots themselves are always kept
+    fn keeps_entry(&self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return true; // Never prune a directory the user explicitly chose
+        }
+        if entry.file_type().is_dir() {
+            let name = entry.file_name().to_string_lossy();
+            if self.pruned_dirs.iter().any(|dir| dir.eq_ignore_ascii_case(&name)) {
+                return false; // Prune the whole subtree
+            }
+            if self.excluded_dirs.iter().any(|dir| entry.path().starts_with(dir)) {
+                return false; // Excluded directories are never walked
+            }
+        }
+        if self.reparse_points == ReparsePolicy::Skip && is_reparse_point(entry) {
+            log::debug!("Skipped the reparse point {}", entry.path().display());
+            return false; // Neither entered nor read
+        }
+        !(self.skip_hidden && is_hidden(entry))
+    }
+
+    // Check whether a file was modified too recently to be scanned; files dated in the future count as recent,
+    // and files without a modification time never do
+    fn is_recent(&self, modified: Option<SystemTime>) -> bool {
+        let (Some(window), Some(modified)) = (self.skip_recent, modified) else { return false };
+        SystemTime::now().duration_since(modified).map_or(true, |age| age < window)
+    }
+
+    // Check whether a file size lies within the size bounds; both bounds are inclusive
+    fn matches_size(&self, size: u64) -> bool {
+        size >= self.min_size && self.max_size.is_none_or(|max| size <= max)
+    }
+
+    // Check whether a file's extension passes the include/exclude lists (case-insensitive)
+    // Files without an extension are only scanned when the include list is empty
+    fn matches_extension(&self, path: &Path) -> bool {
+        let ext = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        match ext {
+            Some(ext) => {
+                !self.exclude_extensions.contains(&ext)
+                    && (self.include_extensions.is_empty() || self.include_extensions.contains(&ext))
+            }
+            None => self.include_extensions.is_empty(),
+        }
+    }
+
+    // Check a file name against the name pattern
+    fn matches_name(&self, path: &Path) -> bool {
+        let Some(pattern) = &self.name_pattern else { return true };
+        path.file_name().is_some_and(|name| pattern.is_match(&name.to_string_lossy()))
+    }
+
+    // Where the files come from, for the log
+    fn sources(&self, dirs: &[String]) -> String {
+        match &self.file_list {
+            Some(list) => format!("{} listed files", list.len()),
+            None => dirs.join(", "),
+        }
+    }
+}
+
+// Files that failed to be read, together with the reason
+pub type ScanErrors = Vec<(PathBuf, io::Error)>;
+
+// Everything produced by a scan
+#[derive(Debug, Default)]
+struct ScanResults {
+    duplicates: HashMap<String, DuplicateGroup>, // Duplicate groups keyed by hash
+    errors: ScanErrors, // Files that couldn't be read or hashed
+    dir_file_counts: HashMap<PathBuf, usize>, // Files found in each scanned folder, subfolders included
+}
+
+// Workers publish their progress at most this often, or after `PROGRESS_BATCH` files, instead of after every file
+// Keeps workers hashing many small files from contending on the shared counters; the UI only needs an approximate value
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+const PROGRESS_BATCH: usize = 256;
+
+// Files at least this big are always shown as the current file, the display would lag behind for a long time otherwise
+const LARGE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+// Files hashed by one worker, with the progress it hasn't published yet
+#[derive(Default)]
+struct HashBatch {
+    file_map: HashMap<(u64, String), Vec<FileEntry>>, // Files grouped by (size, hash)
+    errors: ScanErrors, // Files that failed to hash
+    unreported: usize, // Files processed since the shared counter was last updated
+    reported_at: Option<Instant>, // When this worker last updated the shared progress
+}
+
+impl HashBatch {
+    // Add the unreported files to the shared counter
+    fn report(&mut self, progress: &ScanProgress) {
+        progress.processed.fetch_add(self.unreported, Ordering::Relaxed);
+        self.unreported = 0;
+        self.reported_at = Some(Instant::now());
+    }
+
+    // Whether it's time to update the shared progress again
+    fn report_due(&self) -> bool {
+        self.unreported >= PROGRESS_BATCH || self.reported_at.is_none_or(|at| at.elapsed() >= PROGRESS_INTERVAL)
+    }
+}
+
+// Function to tell whether a file still has the size and modification time it was found with
+// Files inside archives are read from an archive that was found as a whole and always count as unchanged
+pub fn is_unchanged(file: &FileEntry) -> io::Result<bool> {
+    if split_archive_path(&file.path).is_some() {
+        return Ok(true);
+    }
+    let metadata = fs::metadata(extended_path(Path::new(&file.path)))?; // Followed like listed links are
+    Ok(metadata.len() == file.size && metadata.modified().ok() == file.modified)
+}
+
+// Function to check, once a file is hashed, whether it's unchanged since it was found
+// A file written to meanwhile, like a download in progress, gives a hash of neither version
+fn check_unchanged(file: &FileEntry) -> io::Result<()> {
+    if !is_unchanged(file)? {
+        return Err(io::Error::other("skipped (modified during scan)"));
+    }
+    Ok(())
+}
+
+// Function to run the processing of one file, turning a panic into an error of that file
+// A bug or an unexpected OS error on one pathological file then costs that file rather than the whole scan
+fn catch_panic<T>(progress: &ScanProgress, work: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(work)).unwrap_or_else(|payload| {
+        progress.panics.fetch_add(1, Ordering::Relaxed);
+        let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+            (Some(message), _) => message.to_string(),
+            (None, Some(message)) => message.clone(),
+            (None, None) => "unknown cause".to_string(),
+        };
+        Err(io::Error::other(format!("skipped after an internal error ({})", message)))
+    })
+}
+
+// Function to hash files in parallel, grouping them by (size, hash)
+// Returns the grouped files together with the files that failed to hash
+// The processed count is exact once this returns, so a finished phase always shows as complete
+fn hash_in_parallel(
+    files: &[FileEntry],
+    hash: impl Fn(&FileEntry) -> io::Result<String> + Sync,
+    progress: &ScanProgress,
+    pause: &PauseFlag,
+    cancel: &AtomicBool,
+) -> (HashMap<(u64, String), Vec<FileEntry>>, ScanErrors) {
+    // Each worker accumulates into its own map, so workers never contend on a shared one
+    let mut hashed = files
+        .par_iter()
+        .fold(HashBatch::default, |mut batch, file| {
+            // Check whether the scan was paused or cancelled
+            pause.wait(cancel);
+            if cancel.load(Ordering::Relaxed) {
+                return batch; // Skip the remaining files
+            }
+
+            // Show which file is being hashed, without making workers wait on each other
+            if batch.report_due() || file.size >= LARGE_FILE_SIZE {
+                if let Ok(mut current_file) = progress.current_file.try_lock() {
+                    current_file.clone_from(&file.path);
+                }
+            }
+            let path = Path::new(&file.path);
+            // Hash the file, unless it's a moving target
+            let hashed = catch_panic(progress, || hash(file).and_then(|hash| check_unchanged(file).map(|()| hash)));
+            batch.unreported += 1; // Count the file even if hashing failed
+            if batch.report_due() {
+                batch.report(progress);
+            }
+
+            match hashed {
+                Ok(hash) => {
+                    log::debug!("Hashed {}", file.path);
+                    batch.file_map.entry((file.size, hash)).or_default().push(file.clone()) // Record the file under its hash
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {} // Cancelled mid-file, not a real failure
+                Err(e) => {
+                    log::warn!("Failed to hash {}: {}", file.path, e);
+                    batch.errors.push((path.to_path_buf(), e)) // Remember why the file was skipped
+                }
+            }
+            batch
+        })
+        .reduce(HashBatch::default, |mut merged, mut batch| {
+            // Merge the per-worker maps and errors
+            for (key, mut files) in batch.file_map {
+                merged.file_map.entry(key).or_default().append(&mut files);
+            }
+            merged.errors.append(&mut batch.errors);
+            merged.unreported += batch.unreported;
+            merged
+        });
+    hashed.report(progress); // Whatever the workers hadn't published yet
+    (hashed.file_map, hashed.errors)
+}
+
+// Function to walk every directory and hand each file passing the filters to `found`, with its size
+// Files are streamed as the walk proceeds; entries that can't be read are added to `errors`
+fn collect_files(
+    dirs: &[String],
+    filter: &ScanFilter,
+    progress: &ScanProgress,
+    pause: &PauseFlag,
+    cancel: &AtomicBool,
+    errors: &mut ScanErrors,
+    mut found: impl FnMut(FileEntry),
+) {
+    if let Some(list) = &filter.file_list {
+        return list_files(list, filter, progress, pause, cancel, errors, found); // Nothing to walk
+    }
+    let mut seen: HashSet<FileId> = HashSet::new(); // Files already found, in case another path leads to them
+    for (root, result) in dirs.iter().enumerate().flat_map(|(root, dir)| {
+        // Walk each root in turn; WalkDir detects loops itself when following links
+        WalkDir::new(dir)
+            .follow_links(filter.symlinks == SymlinkPolicy::Follow)
+            .max_depth(filter.max_depth.unwrap_or(usize::MAX))
+            .into_iter()
+            .filter_entry(|entry| filter.keeps_entry(entry))
+            .map(move |result| (root, result)) // Remember which root every entry comes from
+    }) {
+        pause.wait(cancel); // Hold the walk while paused
+        if cancel.load(Ordering::Relaxed) {
+            break; // Stop walking once the scan is cancelled
+        }
+        let entry = match result {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Failed to read {}", e);
+                errors.push((e.path().map(Path::to_path_buf).unwrap_or_default(), e.into())); // Remember unreadable entries
+                continue;
+            }
+        };
+        if entry.file_type().is_dir() {
+            if let Ok(mut current_file) = progress.current_file.try_lock() {
+                *current_file = entry.path().display().to_string(); // Show where the walk is, slow file systems take a while
+            }
+        }
+        // The files inside an archive are filtered on their own, whatever the archive's name
+        if filter.archives && entry.file_type().is_file() && is_zip(entry.path()) {
+            scan_archive(entry.path(), root, filter, errors, &mut found);
+        }
+        // Links are only reported as such when they aren't followed
+        let is_link = entry.path_is_symlink() && filter.symlinks == SymlinkPolicy::ListAsFiles;
+        if !(entry.file_type().is_file() || is_link) || !filter.matches_extension(entry.path()) || !filter.matches_name(entry.path()) {
+            continue; // Apply the name filters before any I/O
+        }
+        // Listed links are measured by their target, which is what gets hashed
+        let metadata = if is_link { fs::metadata(extended_path(entry.path())) } else { entry.metadata().map_err(io::Error::from) };
+        match metadata {
+            Ok(metadata) if !metadata.is_file() => {} // Links to directories are never entered
+            Ok(metadata) => {
+                if filter.skip_protected && is_protected(&metadata) {
+                    log::debug!("Skipped {}, it's read-only or a system file", entry.path().display());
+                    continue;
+                }
+                if filter.is_recent(metadata.modified().ok()) {
+                    log::debug!("Skipped {}, it was modified recently", entry.path().display());
+                    continue;
+                }
+                if filter.matches_size(metadata.len()) { // Out-of-range files are never hashed
+                    // A file reached again through a followed link, an overlapping root or a hard link isn't a copy of itself
+                    // Listed links are their own entries, deleting one leaves the target alone
+                    if !is_link && file_id(entry.path(), &metadata).is_some_and(|id| !seen.insert(id)) {
+                        log::debug!("Skipped {}, the same file as a path found before", entry.path().display());
+                        continue;
+                    }
+                    let path = entry.path().display().to_string();
+                    found(FileEntry { path, size: metadata.len(), modified: metadata.modified().ok(), root }); // Handed over right away instead of collecting every file first
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to read the metadata of {}: {}", entry.path().display(), e);
+                errors.push((entry.into_path(), e)) // Remember files whose metadata can't be read
+            }
+        }
+    }
+    progress.current_file.lock().unwrap().clear(); // Done walking
+    progress.discovered.store(true, Ordering::Relaxed);
+}
+
+// Function to hand each file of a list passing the filters to `found`, like `collect_files` without walking anything
+// The paths are made absolute, so they still work from elsewhere; missing files and directories are added to `errors`
+// Only the filters about files apply, the ones about walking have nothing to do; listed links are followed
+fn list_files(
+    list: &[PathBuf],
+    filter: &ScanFilter,
+    progress: &ScanProgress,
+    pause: &PauseFlag,
+    cancel: &AtomicBool,
+    errors: &mut ScanErrors,
+    mut found: impl FnMut(FileEntry),
+) {
+    let mut seen: HashSet<FileId> = HashSet::new(); // A file listed twice isn't a copy of itself
+    for path in list {
+        pause.wait(cancel); // Hold the listing while paused
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+        if filter.archives && is_zip(&path) {
+            scan_archive(&path, 0, filter, errors, &mut found);
+        }
+        if !filter.matches_extension(&path) || !filter.matches_name(&path) {
+            continue; // Apply the name filters before any I/O
+        }
+        match fs::metadata(extended_path(&path)) {
+            Ok(metadata) if !metadata.is_file() => {
+                log::warn!("Skipped {}, it isn't a file", path.display());
+                errors.push((path, io::Error::new(io::ErrorKind::InvalidInput, "listed path isn't a file")));
+            }
+            Ok(metadata) => {
+                if (filter.skip_protected && is_protected(&metadata)) || filter.is_recent(metadata.modified().ok()) || !filter.matches_size(metadata.len()) {
+                    continue;
+                }
+                if file_id(&path, &metadata).is_some_and(|id| !seen.insert(id)) {
+                    log::debug!("Skipped {}, listed before", path.display());
+                    continue;
+                }
+                found(FileEntry { path: path.display().to_string(), size: metadata.len(), modified: metadata.modified().ok(), root: 0 });
+            }
+            Err(e) => {
+                log::warn!("Failed to read the metadata of {}: {}", path.display(), e);
+                errors.push((path, e)); // Like a listed file that no longer exists
+            }
+        }
+    }
+    progress.discovered.store(true, Ordering::Relaxed);
+}
+
+// Function to hand each file inside a zip archive passing the filters to `found`, as a path like `archive.zip::name`
+// The files get the archive's modification time, so cached hashes are dropped when the archive changes
+fn scan_archive(path: &Path, root: usize, filter: &ScanFilter, errors: &mut ScanErrors, found: &mut impl FnMut(FileEntry)) {
+    let modified = fs::metadata(extended_path(path)).and_then(|metadata| metadata.modified()).ok();
+    match archive_entries(path) {
+        Ok(entries) => {
+            for (name, size) in entries {
+                let name_path = Path::new(&name);
+                if filter.matches_extension(name_path) && filter.matches_name(name_path) && filter.matches_size(size) && !filter.is_recent(modified) {
+                    let path = format!("{}{}{}", path.display(), ARCHIVE_SEPARATOR, name);
+                    found(FileEntry { path, size, modified, root });
+                }
+            }
+        }
+        Err(e) => {
+            log::warn!("Failed to read the archive {}: {}", path.display(), e);
+            errors.push((path.to_path_buf(), e)); // Remember archives that can't be opened
+        }
+    }
+}
+
+// Hashes of one file, valid as long as its size and modification time don't change
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedHash {
+    size: u64, // Size of the file when it was hashed
+    modified: SystemTime, // Modification time of the file when it was hashed
+    algo: HashAlgo, // Algorithm the hashes were computed with
+    partial: Option<String>, // Hash of the first PARTIAL_HASH_SIZE bytes, if computed
+    full: Option<String>, // Hash of the whole file, if computed
+}
+
+// Saves of the hash cache started by this process, so saves running at the same time write to different files
+static CACHE_SAVES: AtomicUsize = AtomicUsize::new(0);
+
+// Function to pick a temporary file next to `path`, like "hash_cache.json.1234-0.tmp", used by no other save
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}-{}.tmp", std::process::id(), CACHE_SAVES.fetch_add(1, Ordering::Relaxed)));
+    path.with_file_name(name)
+}
+
+// Hashes from previous scans, keyed by path, so unchanged files aren't read again
+#[derive(Default, Serialize, Deserialize)]
+struct HashCache {
+    entries: HashMap<String, CachedHash>,
+    #[serde(skip)]
+    reused: HashSet<String>, // Files with a hash found during this scan
+    #[serde(skip)]
+    hashed: HashSet<String>, // Files with a hash computed during this scan, for lack of a valid one
+}
+
+// How much reading the hash cache spared a scan
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub reused: usize, // Files whose hashes all came from the cache
+    pub rehashed: usize, // Files that were new, changed or not hashed as far before, so read again
+}
+
+impl HashCache {
+    // Load the cache from disk, starting empty if it's missing or unreadable
+    fn load(path: &Path) -> Self {
+        File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(io::BufReader::new(file)).ok())
+            .unwrap_or_default()
+    }
+
+    // Save the cache to disk, dropping entries of files that no longer exist
+    // Written to a file of its own, then renamed over the old one: another scan saving at the same time, like a stopped
+    // one still winding down, or a crash midway never leaves a truncated or interleaved cache behind
+    fn save(&mut self, path: &Path) -> io::Result<()> {
+        self.entries.retain(|file, _| Path::new(containing_file(file)).exists());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp = temp_path(path);
+        File::create(&temp)
+            .and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                serde_json::to_writer(&mut writer, self)?;
+                writer.flush()
+            })
+            .and_then(|()| fs::rename(&temp, path)) // Replaces the old cache in one step
+            .inspect_err(|_| {
+                let _ = fs::remove_file(&temp); // Don't leave a partial file behind
+            })
+    }
+
+    // Look up a hash, ignoring entries made stale by a change of size or modification time
+    fn get(&self, file: &FileEntry, algo: HashAlgo, full: bool) -> Option<String> {
+        let entry = self.entries.get(&file.path)?;
+        if entry.size != file.size || Some(entry.modified) != file.modified || entry.algo != algo {
+            return None; // The file changed since it was hashed
+        }
+        if full { entry.full.clone() } else { entry.partial.clone() }
+    }
+
+    // Remember a hash, replacing a stale entry
+    fn insert(&mut self, file: &FileEntry, algo: HashAlgo, full: bool, hash: &str) {
+        let Some(modified) = file.modified else { return }; // Changes can't be detected without a modification time
+        let fresh = CachedHash { size: file.size, modified, algo, partial: None, full: None };
+        let entry = self.entries.entry(file.path.clone()).or_insert_with(|| fresh.clone());
+        if entry.size != file.size || entry.modified != modified || entry.algo != algo {
+            *entry = fresh; // The old hashes belong to a previous version of the file
+        }
+        if full {
+            entry.full = Some(hash.to_string());
+        } else {
+            entry.partial = Some(hash.to_string());
+        }
+        self.hashed.insert(file.path.clone());
+    }
+
+    // Files reused and read again so far, a file only counts as reused when none of its hashes had to be computed
+    fn stats(&self) -> CacheStats {
+        CacheStats { reused: self.reused.difference(&self.hashed).count(), rehashed: self.hashed.len() }
+    }
+}
+
+// Function to hash a file through the cache, if there is one
+fn cached_hash(
+    cache: Option<&Mutex<HashCache>>,
+    file: &FileEntry,
+    algo: HashAlgo,
+    full: bool,
+    hash: impl FnOnce() -> io::Result<String>,
+) -> io::Result<String> {
+    let Some(cache) = cache else { return hash() };
+    let mut guard = cache.lock().unwrap();
+    if let Some(hash) = guard.get(file, algo, full) {
+        guard.reused.insert(file.path.clone());
+        return Ok(hash); // Unchanged since the last scan
+    }
+    drop(guard);
+    let hash = hash()?; // Hash without holding the lock, so workers don't wait on each other
+    cache.lock().unwrap().insert(file, algo, full, &hash);
+    Ok(hash)
+}
+
+// Function to log how a scan ended
+fn log_scan_end(groups: usize, errors: usize, cancel: &AtomicBool) {
+    if cancel.load(Ordering::Relaxed) {
+        log::info!("Scan cancelled");
+    } else {
+        log::info!("Scan finished: {} groups found, {} files couldn't be read", groups, errors);
+    }
+}
+
+// Extensions of image files considered by the image similarity mode
+const IMAGE_EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff"];
+
+// Function to check whether a path has an image extension
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// Function to compute a 64-bit difference hash (dHash) of an image
+// Visually similar images get hashes that differ in only a few bits
+fn perceptual_hash(path: &Path) -> io::Result<u64> {
+    let image = image::open(extended_path(path)).map_err(io::Error::other)?; // Undecodable images are reported as errors
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8(); // 9x8 grayscale thumbnail
+
+    // Each bit tells whether a pixel is darker than its right neighbour
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    Ok(hash)
+}
+
+// Function to find groups of visually similar images across one or more directories
+// Images whose perceptual hashes differ in at most `threshold` bits end up in the same group
+fn find_similar_images(
+    dirs: &[String],
+    filter: &ScanFilter,
+    threshold: u32,
+    progress: Arc<ScanProgress>,
+    pause: Arc<PauseFlag>,
+    cancel: Arc<AtomicBool>,
+) -> io::Result<ScanResults> {
+    log::info!("Image similarity scan started in {} (max distance {})", filter.sources(dirs), threshold);
+
+    // Collect every image passing the filters
+    let mut errors = Vec::new(); // Files and directories that couldn't be read or decoded
+    let mut images: Vec<FileEntry> = Vec::new();
+    collect_files(dirs, filter, &progress, &pause, &cancel, &mut errors, |file| {
+        if is_image(Path::new(&file.path)) {
+            progress.files_found.fetch_add(1, Ordering::Relaxed); // Running count for the UI
+            images.push(file);
+        }
+    });
+    progress.total.store(images.len(), Ordering::Relaxed); // Total number of images to decode
+    progress.start_phase(ScanPhase::Comparing, 0.0, 1.0); // Decoding dwarfs the walk
+
+    // Compute the perceptual hash of every image in parallel
+    let (hash_map, hash_errors) = hash_in_parallel(
+        &images,
+        |file| {
+            progress.bytes_read.fetch_add(file.size, Ordering::Relaxed);
+            perceptual_hash(Path::new(&file.path)).map(|hash| format!("{:016x}", hash))
+        },
+        &progress,
+        &pause,
+        &cancel,
+    );
+    errors.extend(hash_errors);
+    progress.current_file.lock().unwrap().clear(); // Nothing is being hashed anymore
+
+    let hashed: Vec<(u64, FileEntry)> = hash_map
+        .into_iter()
+        .flat_map(|((_, hash), files)| {
+            let hash = u64::from_str_radix(&hash, 16).unwrap_or_default();
+            files.into_iter().map(move |file| (hash, file))
+        })
+        .collect();
+
+    // Join images closer than the threshold using a union-find over all pairs
+    let mut parent: Vec<usize> = (0..hashed.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]]; // Path halving keeps the trees shallow
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..hashed.len() {
+        for j in i + 1..hashed.len() {
+            if (hashed[i].0 ^ hashed[j].0).count_ones() <= threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    // Collect the members of every set with more than one image
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashed.len() {
+        members.entry(root(&mut parent, i)).or_default().push(i);
+    }
+    let duplicates = members
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let distance = group
+                .iter()
+                .flat_map(|&a| group.iter().map(move |&b| (a, b)))
+                .map(|(a, b)| (hashed[a].0 ^ hashed[b].0).count_ones())
+                .max()
+                .unwrap_or(0);
+            let key = format!("dhash:{:016x}", hashed[group[0]].0);
+            let duplicate_group = DuplicateGroup {
+                hash: key.clone(),
+                content: key.clone(), // Images this close count as one picture
+                size: group.iter().map(|&i| hashed[i].1.size).min().unwrap_or(0), // Conservative estimate of the space freed
+                files: group.iter().map(|&i| hashed[i].1.clone()).collect(),
+                distance: Some(distance),
+                approximate: false,
+                normalized: false,
+            };
+            (key, duplicate_group)
+        })
+        .collect::<HashMap<_, _>>();
+
+    log_scan_end(duplicates.len(), errors.len(), &cancel);
+    Ok(ScanResults { duplicates, errors, ..ScanResults::default() })
+}
+
+// Function to group files whose sizes differ by at most `tolerance` percent, without reading them
+// A cheap way to narrow down candidates like transcoded videos for manual review: the groups are approximate,
+// their files are merely close in size and may well have nothing in common
+fn find_similar_sizes(
+    dirs: &[String],
+    filter: &ScanFilter,
+    tolerance: f64,
+    progress: Arc<ScanProgress>,
+    pause: Arc<PauseFlag>,
+    cancel: Arc<AtomicBool>,
+) -> io::Result<ScanResults> {
+    log::info!("Similar size scan started in {} (tolerance {}%)", filter.sources(dirs), tolerance);
+
+    let mut errors = Vec::new(); // Directories that couldn't be read
+    let mut files: Vec<FileEntry> = Vec::new();
+    collect_files(dirs, filter, &progress, &pause, &cancel, &mut errors, |file| {
+        progress.files_found.fetch_add(1, Ordering::Relaxed); // Running count for the UI
+        files.push(file);
+    });
+    progress.total.store(files.len(), Ordering::Relaxed);
+    progress.start_phase(ScanPhase::Grouping, 0.0, 1.0); // Nothing is read, only sorted
+
+    // Walk the files from the smallest, each group taking every following file within the tolerance of its first one
+    files.sort_by(|a, b| a.size.cmp(&b.size).then_with(|| a.path.cmp(&b.path)));
+    let mut duplicates = HashMap::new();
+    let mut start = 0;
+    while start < files.len() {
+        let limit = files[start].size as f64 * (1.0 + tolerance / 100.0);
+        let end = start + files[start..].iter().take_while(|file| file.size as f64 <= limit).count();
+        if end - start > 1 {
+            let (min, max) = (files[start].size, files[end - 1].size);
+            let key = format!("size:{}-{}", min, max);
+            let group = DuplicateGroup {
+                hash: key.clone(),
+                content: key.clone(), // Each range stands alone, nothing says its files even match
+                size: min, // Conservative estimate of the space freed
+                files: files[start..end].to_vec(),
+                distance: None,
+                approximate: true,
+                normalized: false,
+            };
+            duplicates.insert(key, group);
+        }
+        start = end;
+    }
+    progress.processed.store(files.len(), Ordering::Relaxed); // Nothing had to be read
+
+    log_scan_end(duplicates.len(), errors.len(), &cancel);
+    Ok(ScanResults { duplicates, errors, ..ScanResults::default() })
+}
+
+// Function to split every group by file name, keeping only the files that share both content and name
+// Groups that had to be split get the name appended to their key, and keep the content of the group they come from
+fn same_name_groups(duplicates: HashMap<String, DuplicateGroup>, case_sensitive: bool) -> HashMap<String, DuplicateGroup> {
+    let mut groups = HashMap::new();
+    for (hash, mut group) in duplicates {
+        let mut by_name: HashMap<String, Vec<FileEntry>> = HashMap::new();
+        for file in std::mem::take(&mut group.files) {
+            let name = Path::new(&file.path).file_name().unwrap_or_default().to_string_lossy();
+            let name = if case_sensitive { name.into_owned() } else { name.to_lowercase() }; // "IMG.JPG" is "img.jpg" unless case matters
+            by_name.entry(name).or_default().push(file);
+        }
+        by_name.retain(|_, files| files.len() > 1); // A name held by a single file has no copy
+        let split = by_name.len() > 1;
+        for (name, files) in by_name {
+            let key = if split { format!("{}:{}", hash, name) } else { hash.clone() };
+            groups.insert(key.clone(), DuplicateGroup { hash: key, files, ..group.clone() });
+        }
+    }
+    groups
+}
+
+// Function to keep only the groups with files from more than one scanned directory
+// The files of each group are ordered by directory, so the first directory's copies come first
+fn cross_root_groups(duplicates: HashMap<String, DuplicateGroup>) -> HashMap<String, DuplicateGroup> {
+    duplicates
+        .into_iter()
+        .filter(|(_, group)| group.files.iter().any(|file| file.root != group.files[0].root)) // Spans at least two roots
+        .map(|(hash, mut group)| {
+            group.files.sort_by(|a, b| a.root.cmp(&b.root).then_with(|| a.path.cmp(&b.path)));
+            (hash, group)
+        })
+        .collect()
+}
+
+// Folders with identical contents
+#[derive(Clone, Debug)]
+pub struct DuplicateDirs {
+    pub size: u64, // Total size of the files in each folder
+    pub files: usize, // Number of files in each folder
+    pub dirs: Vec<String>, // The identical folders, sorted
+}
+
+// Function to find folders with identical contents, meaning the same relative paths holding the same data
+// A folder qualifies when every file scanned in it is a duplicate; its hash combines the sorted (path, hash) pairs
+// of its files, so empty subfolders and filtered out files are not compared
+fn find_duplicate_dirs(duplicates: &HashMap<String, DuplicateGroup>, dir_file_counts: &HashMap<PathBuf, usize>) -> Vec<DuplicateDirs> {
+    // Collect the duplicate files below every scanned folder
+    let mut contents: HashMap<&Path, Vec<(&Path, &str)>> = HashMap::new(); // Relative path and content hash of each file
+    let mut sizes: HashMap<&Path, u64> = HashMap::new();
+    for (hash, group) in duplicates {
+        if group.distance.is_some() || group.approximate || group.normalized {
+            continue; // Similar images and sizes, or normalized text, aren't identical
+        }
+        for file in &group.files {
+            let path = Path::new(&file.path);
+            for dir in path.ancestors().skip(1) {
+                let Some((dir, _)) = dir_file_counts.get_key_value(dir) else { break }; // Above the scanned folders
+                contents.entry(dir).or_default().push((path.strip_prefix(dir).unwrap_or(path), hash.as_str()));
+                *sizes.entry(dir).or_default() += group.size;
+            }
+        }
+    }
+
+    // Hash the contents of the folders holding nothing but duplicates
+    let mut dir_hashes: HashMap<&Path, String> = HashMap::new();
+    let mut by_hash: HashMap<String, Vec<(&Path, usize, u64)>> = HashMap::new();
+    for (dir, mut files) in contents {
+        if files.len() != dir_file_counts[dir] {
+            continue; // Some file in it has no copy
+        }
+        files.sort_unstable();
+        let mut hasher = blake3::Hasher::new();
+        for (path, hash) in &files {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(b"\0");
+            hasher.update(hash.as_bytes());
+            hasher.update(b"\n");
+        }
+        let hash = hasher.finalize().to_hex().to_string();
+        dir_hashes.insert(dir, hash.clone());
+        by_hash.entry(hash).or_default().push((dir, files.len(), sizes[dir]));
+    }
+
+    let mut groups: Vec<DuplicateDirs> = by_hash
+        .into_values()
+        .filter(|dirs| dirs.len() > 1)
+        .filter(|dirs| {
+            // Subfolders of identical folders are identical too, only the topmost folders are reported
+            let parents: HashSet<_> = dirs.iter().map(|(dir, ..)| dir.parent()).collect();
+            let parent_hashes: HashSet<_> = dirs.iter().map(|(dir, ..)| dir.parent().and_then(|parent| dir_hashes.get(parent))).collect();
+            parents.len() < dirs.len() || parent_hashes.len() > 1 || parent_hashes.contains(&None)
+        })
+        .map(|dirs| {
+            let mut paths: Vec<String> = dirs.iter().map(|(dir, ..)| dir.display().to_string()).collect();
+            paths.sort();
+            DuplicateDirs { size: dirs[0].2, files: dirs[0].1, dirs: paths }
+        })
+        .collect();
+    groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.dirs.cmp(&b.dirs))); // Biggest first
+    groups
+}
+
+// Function to fill `buffer` from `file` as far as possible, returning the number of bytes read
+fn fill_buffer(file: &mut dyn Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break, // End of file reached
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// Function to compare two files byte by byte
+pub fn files_equal(a: &str, b: &str) -> io::Result<bool> {
+    with_reader(a, |file_a, size_a| {
+        with_reader(b, |file_b, size_b| {
+            if size_a != size_b {
+                return Ok(false); // Files of different sizes can't be equal
+            }
+
+            let mut buffer_a = vec![0; READ_BUFFER_SIZE]; // Buffer for the first file
+            let mut buffer_b = vec![0; READ_BUFFER_SIZE]; // Buffer for the second file
+            loop {
+                let read_a = fill_buffer(file_a, &mut buffer_a)?;
+                let read_b = fill_buffer(file_b, &mut buffer_b)?;
+                if buffer_a[..read_a] != buffer_b[..read_b] {
+                    return Ok(false); // Contents differ
+                }
+                if read_a == 0 {
+                    return Ok(true); // Both files ended without a difference
+                }
+            }
+        })
+    })
+}
+
+// Function to compare two text files in normalized form, binary and unreadable files never matching
+fn texts_equal(a: &str, b: &str, normalization: TextNormalization) -> bool {
+    let running = AtomicBool::new(false); // Verification runs to the end, like the byte comparison
+    match (read_normalized(a, normalization, &running), read_normalized(b, normalization, &running)) {
+        (Ok(Some(a)), Ok(Some(b))) => a == b,
+        _ => false,
+    }
+}
+
+// Function to split a group of same-hash files into groups whose files are truly `equal`
+fn verify_group(paths: &[String], equal: impl Fn(&str, &str) -> bool) -> Vec<Vec<String>> {
+    let mut groups = Vec::new();
+    let mut remaining = paths.to_vec();
+
+    // Compare every remaining file against the first one until none are left
+    while !remaining.is_empty() {
+        let reference = remaining.remove(0);
+        let (same, different): (Vec<String>, Vec<String>) = remaining
+            .into_iter()
+            .partition(|path| equal(&reference, path));
+        let mut group = vec![reference];
+        group.extend(same);
+        groups.push(group);
+        remaining = different;
+    }
+
+    groups
+}
+
+// Function to run `scan` with its parallel work spread over `threads` workers, 0 meaning one per core
+// A dedicated pool keeps the scan from taking over every core when asked not to
+fn with_thread_pool<T: Send>(threads: usize, scan: impl FnOnce() -> T + Send) -> T {
+    match rayon::ThreadPoolBuilder::new().num_threads(threads).thread_name(|i| format!("dupfinder-worker-{}", i)).build() {
+        Ok(pool) => pool.install(scan),
+        Err(e) => {
+            log::warn!("Failed to start {} worker threads, using the shared pool: {}", threads, e);
+            scan()
+        }
+    }
+}
+
+// Function to verify every duplicate group byte by byte, splitting groups that differ
+// Groups of normalized text are compared in normalized form, as their bytes may differ
+fn verify_duplicates(duplicates: HashMap<String, DuplicateGroup>, normalization: Option<TextNormalization>) -> HashMap<String, DuplicateGroup> {
+    duplicates
+        .into_par_iter()
+        .flat_map_iter(move |(hash, group)| {
+            let paths: Vec<String> = group.files.iter().map(|file| file.path.clone()).collect();
+            let normalization = normalization.filter(|_| group.normalized);
+            verify_group(&paths, |a, b| match normalization {
+                Some(normalization) => texts_equal(a, b, normalization),
+                None => files_equal(a, b).unwrap_or(false), // Unreadable files never match
+            })
+                .into_iter()
+                .filter(|paths| paths.len() > 1) // Keep only groups that are still duplicates
+                .enumerate()
+                .map(move |(i, paths)| {
+                    // Keep the hash as the key, adding a suffix if the group had to be split
+                    let key = if i == 0 { hash.clone() } else { format!("{}#{}", hash, i + 1) };
+                    let files: Vec<FileEntry> = group.files.iter().filter(|file| paths.contains(&file.path)).cloned().collect();
+                    let size = files.iter().map(|file| file.size).min().unwrap_or(group.size); // Normalized text files can differ in size
+                    let (distance, approximate, normalized) = (group.distance, group.approximate, group.normalized);
+                    // Files that turned out to differ hold a content of their own
+                    (key.clone(), DuplicateGroup { content: key.clone(), hash: key, size, files, distance, approximate, normalized })
+                })
+        })
+        .collect()
+}
+
+// Identity of a file on disk, the same for every path leading to it
+#[cfg(unix)]
+type FileId = (u64, u64);
+#[cfg(not(unix))]
+type FileId = PathBuf;
+
+// Function to identify a file by its device and inode on Unix, covering symlinks, hard links and case-insensitive names
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+// Function to identify a file by its canonical path elsewhere, which resolves links and the case of names
+// Hard links keep distinct canonical paths, so they are still reported there
+#[cfg(not(unix))]
+fn file_id(path: &Path, _metadata: &fs::Metadata) -> Option<FileId> {
+    fs::canonicalize(path).ok()
+}
+// Callback receiving the progress of a running scan
+type ProgressCallback = Box<dyn FnMut(ProgressUpdate) + Send>;
+
+// Default time between two progress callbacks, so rendering the progress never slows the scan down
+const CALLBACK_INTERVAL: Duration = Duration::from_millis(250);
+
+// Callback receiving copies found while a scan runs: their hash, their size and the files that are new to the group
+type DuplicatesCallback = Box<dyn Fn(&str, u64, &[FileEntry]) + Send + Sync>;
+
+// Files hashed so far, to report every group as soon as a second copy turns up
+// Only the first file of a hash is kept until then, later ones are reported right away
+struct LiveGroups<'a> {
+    seen: Mutex<HashMap<(u64, String), Option<FileEntry>>>, // First file of each (size, hash), None once reported
+    report: &'a (dyn Fn(&str, u64, &[FileEntry]) + Send + Sync), // Where the copies go
+}
+
+impl<'a> LiveGroups<'a> {
+    fn new(report: &'a (dyn Fn(&str, u64, &[FileEntry]) + Send + Sync)) -> Self {
+        Self { seen: Mutex::default(), report }
+    }
+
+    // Record a hashed file, reporting it along with the first copy if it has one
+    fn add(&self, file: &FileEntry, hash: &str) {
+        let new = match self.seen.lock().unwrap().entry((file.size, hash.to_string())) {
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(Some(file.clone()));
+                return; // No copy yet
+            }
+            std::collections::hash_map::Entry::Occupied(mut entry) => match entry.get_mut().take() {
+                Some(first) => vec![first, file.clone()],
+                None => vec![file.clone()],
+            },
+        };
+        (self.report)(hash, file.size, &new); // Outside the lock, so workers don't wait on the receiver
+    }
+}
+
+// How often a running scan reports its progress and checks that its directories are still there
+const MONITOR_INTERVAL: Duration = Duration::from_millis(100);
+
+// Function to find a scanned directory that no longer exists, like a deleted folder or an unplugged drive
+fn missing_dir(dirs: &[String]) -> Option<&str> {
+    dirs.iter().map(String::as_str).find(|dir| !Path::new(dir).exists())
+}
+
+// Error returned when a scanned directory is gone, the rest of a scan would only produce a flood of read errors
+fn dir_unavailable(dir: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("The scanned directory {} is no longer available", dir))
+}
+
+// Everything a finished scan reports
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub groups: Vec<DuplicateGroup>, // Duplicate groups, the most wasted space first
+    pub duplicate_dirs: Vec<DuplicateDirs>, // Folders holding identical copies of each other, if requested
+    pub errors: ScanErrors, // Files that couldn't be read or hashed, and a hash cache that couldn't be saved
+    pub verified: bool, // Whether every group was confirmed byte by byte
+    pub cache: Option<CacheStats>, // How many files the hash cache spared, if one was used
+}
+
+// A configurable duplicate scan over one or more directories
+// Built with `DupScanner::new(dirs)` and the option methods, then run with `scan`:
+//     let report = DupScanner::new(vec!["/home/me/Photos".into()]).algorithm(HashAlgo::Blake3).verify(true).scan()?;
+pub struct DupScanner {
+    dirs: Vec<String>, // Directories to scan
+    algo: HashAlgo, // Hash comparing the file contents
+    filter: ScanFilter, // Which files are considered
+    threads: usize, // Worker threads, 0 meaning one per core
+    verify: bool, // Compare hash matches byte by byte
+    same_name: bool, // Only keep copies sharing their file name
+    case_sensitive_names: bool, // Whether names differing by case are different when matching names
+    compare: bool, // Only keep groups spanning several directories
+    similar_images: Option<u32>, // Group similar images within this perceptual-hash distance instead of exact copies
+    similar_sizes: Option<f64>, // Group files within this size tolerance in percent instead of exact copies
+    normalize_text: Option<TextNormalization>, // How text files are normalized before hashing, None to hash raw bytes
+    duplicate_dirs: bool, // Also report whole duplicate folders
+    cache: Option<PathBuf>, // Where hashes are remembered between scans, if anywhere
+    progress: Arc<ScanProgress>, // Live counters of the scan
+    pause: Arc<PauseFlag>, // Switch pausing the scan
+    cancel: Arc<AtomicBool>, // Flag stopping the scan
+    on_progress: Option<Mutex<ProgressCallback>>, // Called regularly with the progress, only ever from the monitor thread
+    progress_interval: Duration, // Minimum time between two progress callbacks
+    on_duplicates: Option<DuplicatesCallback>, // Called with copies as soon as they're found
+}
+
+impl DupScanner {
+    // Create a scanner for the given directories with the default options: SHA-256, no filters and every core
+    pub fn new(dirs: Vec<String>) -> Self {
+        Self {
+            dirs,
+            algo: HashAlgo::Sha256,
+            filter: ScanFilter::default(),
+            threads: 0,
+            verify: false,
+            same_name: false,
+            case_sensitive_names: CASE_SENSITIVE_NAMES,
+            compare: false,
+            similar_images: None,
+            similar_sizes: None,
+            normalize_text: None,
+            duplicate_dirs: false,
+            cache: None,
+            progress: Arc::new(ScanProgress::default()),
+            pause: Arc::new(PauseFlag::default()),
+            cancel: Arc::new(AtomicBool::new(false)),
+            on_progress: None,
+            progress_interval: CALLBACK_INTERVAL,
+            on_duplicates: None,
+        }
+    }
+
+    // Hash algorithm comparing the file contents
+    pub fn algorithm(mut self, algo: HashAlgo) -> Self {
+        self.algo = algo;
+        self
+    }
+
+    // Filters deciding which files are scanned
+    pub fn filter(mut self, filter: ScanFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // Number of worker threads, 0 meaning one per core
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    // Whether hash matches are compared byte by byte to rule out collisions
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    // Whether only copies sharing their file name are reported
+    pub fn same_name(mut self, same_name: bool) -> Self {
+        self.same_name = same_name;
+        self
+    }
+
+    // Whether names differing only by case, like "IMG.JPG" and "img.jpg", count as different when matching names,
+    // by default this platform's convention
+    pub fn case_sensitive_names(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive_names = case_sensitive;
+        self
+    }
+
+    // Whether only groups with files in more than one of the directories are reported
+    pub fn compare(mut self, compare: bool) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    // Group visually similar images within the given perceptual-hash distance instead of exact copies
+    pub fn similar_images(mut self, threshold: Option<u32>) -> Self {
+        self.similar_images = threshold;
+        self
+    }
+
+    // Group files whose sizes differ by at most the given percentage instead of exact copies, without reading them
+    // The groups are marked approximate: the files are only candidates for a closer look, never safe to delete
+    pub fn similar_sizes(mut self, tolerance: Option<f64>) -> Self {
+        self.similar_sizes = tolerance;
+        self
+    }
+
+    // Hash the text files in normalized form, so copies differing only in line endings or trailing whitespace match
+    // Only files with a text extension and UTF-8 content are normalized, everything else is still compared byte for byte
+    pub fn normalize_text(mut self, normalization: Option<TextNormalization>) -> Self {
+        self.normalize_text = normalization;
+        self
+    }
+
+    // Whether whole folders of identical files are reported too
+    pub fn duplicate_dirs(mut self, duplicate_dirs: bool) -> Self {
+        self.duplicate_dirs = duplicate_dirs;
+        self
+    }
+
+    // File remembering hashes between scans, None to hash everything again
+    pub fn cache(mut self, path: Option<PathBuf>) -> Self {
+        self.cache = path;
+        self
+    }
+
+    // Function called with the progress while the scan runs, at most once per progress interval and only when
+    // something changed, plus once with the final counters; it runs on a thread of its own, never blocking the workers
+    pub fn on_progress(mut self, callback: impl FnMut(ProgressUpdate) + Send + 'static) -> Self {
+        self.on_progress = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    // Minimum time between two progress callbacks, 250 ms by default
+    // The directories are checked every 100 ms, so shorter intervals behave like that one
+    pub fn progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    // Function called with copies as soon as they're hashed, before the groups are verified and filtered at the end
+    // Gets the hash, the size and the files new to the group; not called when looking for similar images
+    pub fn on_duplicates(mut self, callback: impl Fn(&str, u64, &[FileEntry]) + Send + Sync + 'static) -> Self {
+        self.on_duplicates = Some(Box::new(callback));
+        self
+    }
+
+    // Live counters of the scan, for polling from another thread
+    pub fn progress(&self) -> Arc<ScanProgress> {
+        Arc::clone(&self.progress)
+    }
+
+    // Switch pausing and resuming the scan from another thread
+    pub fn pause_flag(&self) -> Arc<PauseFlag> {
+        Arc::clone(&self.pause)
+    }
+
+    // Flag stopping the scan from another thread once set
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.cancel)
+    }
+
+    // Run the scan, blocking until it's done or cancelled
+    // A cancelled scan returns what was found so far, unverified
+    // Fails with `NotFound` if a directory is missing or disappears while it's scanned
+    pub fn scan(&self) -> io::Result<ScanReport> {
+        if let Some(dir) = missing_dir(&self.dirs) {
+            return Err(dir_unavailable(dir));
+        }
+        let (finished, ticks) = mpsc::channel::<()>(); // Dropped once the scan is over, waking the monitor up
+        std::thread::scope(|scope| {
+            // Report the progress and abort the scan as soon as one of its directories is gone
+            let monitor = scope.spawn(move || {
+                let mut lost = None;
+                let mut reported: Option<(Instant, ProgressUpdate)> = None; // Last progress handed to the callback
+                while lost.is_none() && ticks.recv_timeout(MONITOR_INTERVAL) == Err(mpsc::RecvTimeoutError::Timeout) {
+                    if let Some(callback) = &self.on_progress {
+                        if reported.as_ref().is_none_or(|(at, _)| at.elapsed() >= self.progress_interval) {
+                            let update = self.progress.snapshot();
+                            if reported.as_ref().is_none_or(|(_, last)| *last != update) {
+                                (callback.lock().unwrap())(update.clone()); // Nothing new while paused or stuck on a slow file
+                            }
+                            reported = Some((Instant::now(), update));
+                        }
+                    }
+                    lost = missing_dir(&self.dirs).map(str::to_string);
+                }
+                if let Some(dir) = &lost {
+                    log::warn!("Scan aborted, {} is no longer available", dir);
+                    self.cancel.store(true, Ordering::Relaxed);
+                    self.pause.set(false); // Paused workers have to wake up to notice
+                }
+                if let Some(callback) = &self.on_progress {
+                    (callback.lock().unwrap())(self.progress.snapshot()); // The final counters
+                }
+                lost
+            });
+            let report = with_thread_pool(self.threads, || self.run());
+            drop(finished);
+            match monitor.join().unwrap() {
+                Some(dir) => Err(dir_unavailable(&dir)),
+                None => report,
+            }
+        })
+    }
+
+    // Find duplicate files across the directories (using multithreading)
+    fn find_duplicates(&self, cache: Option<&Mutex<HashCache>>) -> io::Result<ScanResults> {
+        let (dirs, algo, filter) = (&self.dirs, self.algo, &self.filter);
+        let (progress, pause, cancel) = (&self.progress, &self.pause, &self.cancel);
+        let live = self.on_duplicates.as_deref().map(LiveGroups::new); // Reports copies as soon as they're hashed
+        log::info!("Scan started in {} ({})", filter.sources(dirs), algo.label());
+
+        // Group all files in every directory and its subdirectories by size
+        let mut errors = Vec::new(); // Files and directories that couldn't be read
+        let mut size_map: HashMap<u64, Vec<FileEntry>> = HashMap::new();
+        let mut dir_file_counts: HashMap<PathBuf, usize> = HashMap::new(); // Needed to tell whether whole folders are duplicates
+        collect_files(dirs, filter, progress, pause, cancel, &mut errors, |file| {
+            progress.files_found.fetch_add(1, Ordering::Relaxed); // Running count for the UI
+            // Listed files are no root's, and their folders are never scanned whole
+            let root = dirs.get(file.root).filter(|_| filter.file_list.is_none()).map(Path::new);
+            for dir in Path::new(&file.path).ancestors().skip(1).take_while(|_| root.is_some()) {
+                *dir_file_counts.entry(dir.to_path_buf()).or_default() += 1;
+                if Some(dir) == root {
+                    break; // Folders above the scanned one are only partly scanned
+                }
+            }
+            size_map.entry(file.size).or_default().push(file);
+        });
+
+        progress.start_phase(ScanPhase::Grouping, 0.0, 0.0);
+
+        // Normalized text files can match whatever their size, so they're set apart from the grouping by size
+        // and compared among themselves
+        let mut texts = Vec::new();
+        if self.normalize_text.is_some() {
+            for files in size_map.values_mut() {
+                let (text, other): (Vec<FileEntry>, Vec<FileEntry>) = std::mem::take(files).into_iter().partition(is_text_candidate);
+                texts.extend(text);
+                *files = other;
+            }
+        }
+
+        // Only files sharing a size with at least one other file can be duplicates
+        let candidates: Vec<FileEntry> = size_map.into_values().filter(|files| files.len() > 1).flatten().collect();
+
+        progress.total.store(candidates.len(), Ordering::Relaxed); // Total number of files to be hashed in the first phase
+        let first_bytes: u64 = candidates.iter().map(|file| file.size.min(PARTIAL_HASH_SIZE)).sum();
+        progress.bytes_total.store(first_bytes, Ordering::Relaxed); // Bytes to go through, for a progress that follows the I/O
+        // Weigh the phases: the walk is over, the first blocks are known, and at most every candidate larger than
+        // a block is read whole; the second phase then gets whatever is left, however many candidates remain
+        let walk = progress.files_found.load(Ordering::Relaxed) as u64 * FILE_COST;
+        let first = first_bytes + candidates.len() as u64 * FILE_COST;
+        let larger = candidates.iter().filter(|file| file.size > PARTIAL_HASH_SIZE);
+        let second: u64 = larger.chain(&texts).map(|file| file.size + FILE_COST).sum();
+        let all = (walk + first + second).max(1) as f64;
+        progress.start_phase(ScanPhase::PartialHash, walk as f64 / all, first as f64 / all);
+
+        // Phase 1: hash only the first block of each candidate, most same-size files already differ there
+        let (partial_map, partial_errors) = hash_in_parallel(
+            &candidates,
+            |file| {
+                let hash = progress.track(file.size.min(PARTIAL_HASH_SIZE), |counted| {
+                    cached_hash(cache, file, algo, false, || hash_file_tracked(&file.path, algo, PARTIAL_HASH_SIZE, cancel, progress, counted))
+                })?;
+                if let Some(live) = live.as_ref().filter(|_| file.size <= PARTIAL_HASH_SIZE) {
+                    live.add(file, &hash); // The first block was the whole file
+                }
+                Ok(hash)
+            },
+            progress,
+            pause,
+            cancel,
+        );
+
+        errors.extend(partial_errors);
+
+        // Files whose (size, partial hash) bucket is shared still need a full hash
+        let mut file_map: HashMap<String, DuplicateGroup> = HashMap::new(); // To store hashes and their corresponding files
+        let mut full_candidates = Vec::new();
+        for ((size, partial_hash), files) in partial_map {
+            if files.len() < 2 {
+                continue; // Unique first block, can't be a duplicate
+            }
+            if size <= PARTIAL_HASH_SIZE {
+                // The first block was the whole file, so the partial hash is already the full hash
+                file_map.insert(partial_hash.clone(), DuplicateGroup::new(partial_hash, size, files));
+            } else {
+                full_candidates.extend(files);
+            }
+        }
+
+        // Phase 2: fully hash the remaining candidates
+        let base = progress.phase_end();
+        progress.start_phase(ScanPhase::FullHash, base, 1.0 - base); // Before the totals grow, so the first phase stays complete
+        progress.total.fetch_add(full_candidates.len() + texts.len(), Ordering::Relaxed); // Account for the second phase, texts included
+        progress.bytes_total.fetch_add(full_candidates.iter().chain(&texts).map(|file| file.size).sum(), Ordering::Relaxed);
+        let (full_map, full_errors) = hash_in_parallel(
+            &full_candidates,
+            |file| {
+                let hash = progress.track(file.size, |counted| {
+                    cached_hash(cache, file, algo, true, || hash_file_tracked(&file.path, algo, u64::MAX, cancel, progress, counted))
+                })?;
+                if let Some(live) = &live {
+                    live.add(file, &hash);
+                }
+                Ok(hash)
+            },
+            progress,
+            pause,
+            cancel,
+        );
+        for ((size, hash), files) in full_map {
+            file_map.insert(hash.clone(), DuplicateGroup::new(hash, size, files));
+        }
+        errors.extend(full_errors);
+
+        // Text files are read whole and hashed once normalized, binary ones among them get their raw hash
+        if let Some(normalization) = self.normalize_text {
+            let (text_map, text_errors) = hash_in_parallel(
+                &texts,
+                |file| {
+                    progress.bytes_read.fetch_add(file.size, Ordering::Relaxed);
+                    progress.track(file.size, |_| hash_normalized(&file.path, algo, normalization, cancel)) // Read whole, so counted at once
+                },
+                progress,
+                pause,
+                cancel,
+            );
+            // Grouped by hash alone, whatever the size; a hash the other files already have joins their group
+            for ((_, hash), files) in text_map {
+                let normalized = hash.starts_with(NORMALIZED_PREFIX);
+                let hash = hash.strip_prefix(NORMALIZED_PREFIX).unwrap_or(&hash).to_string();
+                let group = file_map.entry(hash.clone()).or_insert_with(|| DuplicateGroup::new(hash, u64::MAX, Vec::new()));
+                group.size = files.iter().map(|file| file.size).fold(group.size, u64::min); // The smallest copy, for a conservative estimate
+                group.files.extend(files);
+                group.normalized |= normalized;
+            }
+            errors.extend(text_errors);
+        }
+
+        progress.current_file.lock().unwrap().clear(); // Nothing is being hashed anymore
+
+        // Filter out the duplicates from the file_map
+        let duplicates = file_map
+            .into_iter()
+            .filter(|(_, group)| group.files.len() > 1) // Keep only hashes with multiple files
+            .collect::<HashMap<_, _>>(); // Collect as a HashMap
+
+        log_scan_end(duplicates.len(), errors.len(), cancel);
+        Ok(ScanResults { duplicates, errors, dir_file_counts }) // Return the duplicates, the failures and what's needed to compare folders
+    }
+
+    // Every step of the scan, run on the worker pool
+    fn run(&self) -> io::Result<ScanReport> {
+        let cache = self.cache.as_deref().map(|path| Mutex::new(HashCache::load(path)));
+        let (progress, pause, cancel) = (Arc::clone(&self.progress), Arc::clone(&self.pause), Arc::clone(&self.cancel));
+        let results = match (self.similar_images, self.similar_sizes) {
+            (Some(threshold), _) => find_similar_images(&self.dirs, &self.filter, threshold, progress, pause, cancel),
+            (None, Some(tolerance)) => find_similar_sizes(&self.dirs, &self.filter, tolerance, progress, pause, cancel),
+            (None, None) => self.find_duplicates(cache.as_ref()),
+        };
+        // Keep the hashes for next time, even those computed by a stopped or failed scan
+        let exact = self.similar_images.is_none() && self.similar_sizes.is_none(); // Otherwise the files aren't byte-identical
+        let mut cache_stats = None; // Only exact scans go through the cache
+        let cache_error = match (&self.cache, cache) {
+            (Some(path), Some(cache)) => {
+                let mut cache = cache.into_inner().unwrap();
+                cache_stats = exact.then(|| cache.stats());
+                cache.save(path).err().map(|e| (path.clone(), e))
+            }
+            _ => None,
+        };
+        let mut results = results?;
+        results.errors.extend(cache_error); // Reported like any other unwritable file
+
+        let mut duplicates = results.duplicates;
+        let cancelled = self.cancel.load(Ordering::Relaxed);
+        let verified = self.verify && exact && !cancelled;
+        if verified {
+            duplicates = verify_duplicates(duplicates, self.normalize_text); // Rule out hash collisions
+        }
+        if self.same_name {
+            duplicates = same_name_groups(duplicates, self.case_sensitive_names); // Only copies with the same name
+        }
+        if self.compare {
+            duplicates = cross_root_groups(duplicates); // After verifying, which may split groups
+        }
+        // Based on the final groups, so folders only match when all their files survived verification
+        let duplicate_dirs = if self.duplicate_dirs && exact {
+            find_duplicate_dirs(&duplicates, &results.dir_file_counts)
+        } else {
+            Vec::new()
+        };
+
+        let mut groups: Vec<DuplicateGroup> = duplicates.into_values().collect();
+        groups.sort_by(|a, b| b.wasted_space().cmp(&a.wasted_space()).then_with(|| a.hash.cmp(&b.hash)));
+        Ok(ScanReport { groups, duplicate_dirs, errors: results.errors, verified, cache: cache_stats })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Function to write a file into `dir`, returning its path
+    fn write_file(dir: &Path, name: &str, contents: &[u8]) -> String {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path.display().to_string()
+    }
+
+    // Function to build the entry a scan would list for a file as it is right now
+    fn listed(path: &str) -> FileEntry {
+        let metadata = fs::metadata(path).unwrap();
+        FileEntry { path: path.to_string(), size: metadata.len(), modified: metadata.modified().ok(), root: 0 }
+    }
+
+    #[test]
+    fn unreadable_file_still_completes_the_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = listed(&write_file(dir.path(), "kept.bin", &[7; 10_000]));
+        let deleted = listed(&write_file(dir.path(), "deleted.bin", &[7; 10_000]));
+        fs::remove_file(&deleted.path).unwrap(); // Gone between listing and hashing
+        let files = vec![kept, deleted];
+
+        // Set up like the full-hash phase of a scan, counting bytes
+        let progress = ScanProgress::default();
+        progress.total.store(files.len(), Ordering::Relaxed);
+        progress.bytes_total.store(files.iter().map(|file| file.size).sum(), Ordering::Relaxed);
+        progress.start_phase(ScanPhase::FullHash, 0.0, 1.0);
+        let cancel = AtomicBool::new(false);
+        let (hashed, errors) = hash_in_parallel(
+            &files,
+            |file| progress.track(file.size, |counted| hash_file_tracked(&file.path, HashAlgo::Sha256, u64::MAX, &cancel, &progress, counted)),
+            &progress,
+            &PauseFlag::default(),
+            &cancel,
+        );
+
+        assert_eq!(hashed.values().map(Vec::len).sum::<usize>(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1.kind(), io::ErrorKind::NotFound);
+        assert_eq!(progress.processed.load(Ordering::Relaxed), progress.total.load(Ordering::Relaxed)); // The failed file counts too
+        assert_eq!(progress.bytes_done.load(Ordering::Relaxed), progress.bytes_total.load(Ordering::Relaxed));
+        assert_eq!(progress.fraction(), 1.0);
+    }
+
+    // A file that never ends, cancelling the scan once it has handed out `cancel_after` chunks
+    struct EndlessReader<'a> {
+        reads: usize,
+        cancel_after: usize,
+        cancel: &'a AtomicBool,
+    }
+
+    impl Read for EndlessReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            if self.reads == self.cancel_after {
+                self.cancel.store(true, Ordering::Relaxed); // Like the Stop button, pressed mid-file
+            }
+            buf.fill(0);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn cancelling_stops_reading_a_large_file() {
+        let cancel = AtomicBool::new(false);
+        let mut reader = EndlessReader { reads: 0, cancel_after: 3, cancel: &cancel };
+        let started = Instant::now();
+        let result = read_chunks(&mut reader, &cancel, |_| {});
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(reader.reads <= CANCEL_CHECK_INTERVAL, "read {} chunks after being cancelled", reader.reads); // At the next check
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    // A file whose read fails after `chunks` chunks, like a network share dropping mid-file
+    struct FailingReader {
+        chunks: usize,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.chunks == 0 {
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, "connection lost"));
+            }
+            self.chunks -= 1;
+            buf.fill(1);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn failed_read_gives_no_hash() {
+        let cancel = AtomicBool::new(false);
+        for algo in [HashAlgo::Sha256, HashAlgo::Blake3, HashAlgo::XxHash] {
+            let result = hash_reader(FailingReader { chunks: 3 }, algo, &cancel);
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::ConnectionReset, "{} hashed the partial data", algo.label());
+        }
+    }
+
+    #[test]
+    fn scan_groups_identical_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write_file(dir.path(), "a.txt", b"the same content");
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let b = write_file(&dir.path().join("sub"), "b.txt", b"the same content");
+        write_file(dir.path(), "c.txt", b"other  content!!"); // Same size, different bytes
+        write_file(dir.path(), "d.txt", b"shorter");
+
+        let report = DupScanner::new(vec![dir.path().display().to_string()]).scan().unwrap();
+        assert_eq!(report.groups.len(), 1);
+        let mut paths: Vec<&str> = report.groups[0].files.iter().map(|file| file.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, [a.as_str(), b.as_str()]);
+        assert_eq!(report.groups[0].size, 16);
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn missing_directory_fails_the_scan() {
+        let kept = tempfile::tempdir().unwrap();
+        let removed = tempfile::tempdir().unwrap();
+        let removed_path = removed.path().display().to_string();
+        removed.close().unwrap(); // Like an unplugged drive
+
+        let scanner = DupScanner::new(vec![kept.path().display().to_string(), removed_path.clone()]);
+        let e = scanner.scan().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains(&removed_path));
+    }
+
+    #[test]
+    fn directory_removed_mid_scan_fails_the_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("drive");
+        fs::create_dir(&root).unwrap();
+        for i in 0..50 {
+            write_file(&root, &format!("{}.txt", i), b"the same content");
+        }
+
+        // Once hashing has begun, hold it and take the directory away, like a drive unplugged mid-scan
+        let scanner = DupScanner::new(vec![root.display().to_string()]);
+        let (pause, progress) = (scanner.pause_flag(), scanner.progress());
+        let removed = Arc::new(AtomicBool::new(false));
+        let (flag, unplugged) = (Arc::clone(&removed), root.clone());
+        let scanner = scanner.on_duplicates(move |_, _, _| {
+            if !flag.swap(true, Ordering::Relaxed) {
+                pause.set(true); // The other files wait, so the scan can't finish before the directory is missed
+                fs::remove_dir_all(&unplugged).unwrap();
+            }
+        });
+
+        let e = scanner.scan().unwrap_err();
+        assert!(removed.load(Ordering::Relaxed));
+        assert!(progress.discovered.load(Ordering::Relaxed)); // Past the check made before the scan starts
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert!(e.to_string().contains(&root.display().to_string()));
+        assert!(scanner.cancel_flag().load(Ordering::Relaxed)); // The workers were told to stop
+    }
+
+    #[test]
+    fn groups_split_by_name_keep_their_content() {
+        let file = |path: &str| FileEntry { path: path.to_string(), size: 4, modified: None, root: 0 };
+        let files = vec![file("/a/photo.jpg"), file("/b/photo.jpg"), file("/a/copy.jpg"), file("/b/copy.jpg")];
+        let duplicates = HashMap::from([("1f3a".to_string(), DuplicateGroup::new("1f3a".to_string(), 4, files))]);
+
+        let split = same_name_groups(duplicates, true);
+        let mut keys: Vec<&str> = split.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["1f3a:copy.jpg", "1f3a:photo.jpg"]);
+        assert!(split.values().all(|group| group.content == "1f3a"));
+    }
+
+    #[test]
+    fn file_changed_after_listing_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = listed(&write_file(dir.path(), "kept.bin", b"0123456789"));
+        let rewritten = listed(&write_file(dir.path(), "rewritten.bin", b"0123456789"));
+        let touched = listed(&write_file(dir.path(), "touched.bin", b"0123456789"));
+        fs::write(&rewritten.path, b"0123456789 and more").unwrap(); // Grown, like a download in progress
+        let later = touched.modified.unwrap() + Duration::from_secs(3600);
+        File::options().write(true).open(&touched.path).unwrap().set_modified(later).unwrap(); // Same size, newer time
+
+        assert!(check_unchanged(&kept).is_ok());
+        assert!(check_unchanged(&rewritten).is_err());
+        assert!(check_unchanged(&touched).is_err());
+
+        // During a scan, the changed files are reported rather than grouped with the unchanged one
+        let files = vec![kept, rewritten, touched];
+        let cancel = AtomicBool::new(false);
+        let progress = ScanProgress::default();
+        let (hashed, errors) = hash_in_parallel(&files, |file| hash_file(&file.path, HashAlgo::Sha256, &cancel), &progress, &PauseFlag::default(), &cancel);
+        assert_eq!(hashed.values().map(Vec::len).sum::<usize>(), 1);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|(_, e)| e.to_string().contains("modified during scan")));
+    }
+
+    #[test]
+    fn concurrent_cache_saves_leave_a_valid_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join("hash_cache.json");
+        fs::create_dir(dir.path().join("files")).unwrap();
+        let files: Vec<FileEntry> = (0..800).map(|i| listed(&write_file(&dir.path().join("files"), &format!("{}.bin", i), b"x"))).collect();
+
+        // Like a stopped scan still saving while a new one saves too, each cache large enough to take many writes
+        // and of its own size, so a mix of two would show
+        std::thread::scope(|scope| {
+            for saver in 1..=4 {
+                let (path, files) = (&path, &files[..saver * 200]);
+                scope.spawn(move || {
+                    let mut cache = HashCache::default();
+                    for file in files {
+                        cache.insert(file, HashAlgo::Sha256, true, &"0f".repeat(32));
+                    }
+                    for _ in 0..10 {
+                        cache.save(path).unwrap();
+                    }
+                });
+            }
+        });
+
+        let saved: HashCache = serde_json::from_reader(File::open(&path).unwrap()).unwrap(); // Whole, not interleaved
+        assert!([200, 400, 600, 800].contains(&saved.entries.len()));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1); // No temporary file left over
+    }
+
+    #[test]
+    fn stop_after_completion_ends_idle() {
+        let state = SharedScanState::new(ScanState::Scanning);
+        assert!(state.finish()); // The thread got there first and publishes its outcome
+        assert_eq!(state.get(), ScanState::Done);
+        state.stop();
+        assert_eq!(state.get(), ScanState::Idle);
+    }
+
+    #[test]
+    fn completion_after_stop_publishes_nothing() {
+        let state = SharedScanState::new(ScanState::Scanning);
+        state.stop();
+        assert_eq!(state.get(), ScanState::Stopping); // The thread is still winding down
+        assert!(!state.finish());
+        assert_eq!(state.get(), ScanState::Idle);
+    }
+
+    #[test]
+    fn stop_without_a_running_scan_stays_idle() {
+        for initial in [ScanState::Idle, ScanState::Done] {
+            let state = SharedScanState::new(initial);
+            state.stop();
+            assert_eq!(state.get(), ScanState::Idle);
+            assert!(!state.finish()); // A thread that isn't there can't complete either
+            assert_eq!(state.get(), ScanState::Idle);
+        }
+    }
+
+    #[test]
+    fn stop_racing_completion_always_ends_idle() {
+        for i in 0..2000 {
+            let state = Arc::new(SharedScanState::new(ScanState::Scanning));
+            let thread_state = Arc::clone(&state);
+            let thread = std::thread::spawn(move || thread_state.finish());
+            if i % 2 == 0 {
+                std::thread::yield_now(); // Let either side go first
+            }
+            state.stop();
+            let published = thread.join().unwrap();
+            assert_eq!(state.get(), ScanState::Idle, "published: {}", published);
+        }
+    }
+}
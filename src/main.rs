@@ -1,189 +1,3960 @@
 #![windows_subsystem = "windows"]
 
 // Import necessary modules and crates
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{self, Read};
-use std::path::Path;
-use std::sync::{Arc, Mutex, mpsc};
-use sha2::{Digest, Sha256};
-use walkdir::WalkDir;
-use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use regex::{Regex, RegexBuilder};
 use eframe::{egui, App, Frame};
 use rfd::FileDialog;
+use serde::{Deserialize, Serialize};
+use clap::Parser;
+use similar::ChangeTag;
+use dupfinder::{containing_file, extended_path, files_equal, is_image, is_unchanged, parse_name_pattern, split_archive_path, with_reader, CASE_SENSITIVE_NAMES, CacheStats, DupScanner, DuplicateDirs, DuplicateGroup, FileEntry, HashAlgo, PatternKind, PauseFlag, ProgressUpdate, ReparsePolicy, ScanErrors, ScanFilter, ScanProgress, ScanState, SharedScanState, SymlinkPolicy, TextNormalization};
 
-// Function to hash a file using SHA-256
-fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
-    let mut file = File::open(path)?; // Attempt to open the file
-    let mut hasher = Sha256::new(); // Create a new SHA-256 hasher
-    let mut buffer = vec![0; 4096]; // Buffer to hold file data
+// Function to format a size in bytes with human-readable units
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0; // Move to the next larger unit
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0]) // Whole bytes need no decimals
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Function to parse a comma-separated list into its trimmed, non-empty items
+fn parse_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// Function to parse a comma-separated extension list like ".jpg, PNG" into lowercase extensions
+fn parse_extensions(list: &str) -> Vec<String> {
+    parse_list(list)
+        .into_iter()
+        .map(|ext| ext.trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
 
-    // Read the file in chunks and update the hasher
-    while let Ok(bytes_read) = file.read(&mut buffer) {
-        if bytes_read == 0 {
-            break; // Break the loop if no more bytes are read
+// Function to compile the query filtering the results, returning None when it's empty
+// Plain queries match any part of a path; both kinds ignore case
+fn parse_results_query(query: &str, regex: bool) -> Result<Option<Regex>, regex::Error> {
+    if query.is_empty() {
+        return Ok(None); // No query, every group is shown
+    }
+    let pattern = if regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&pattern).case_insensitive(true).build().map(Some)
+}
+
+// Appearance of the application
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum Theme {
+    System, // Follow the operating system's dark/light setting
+    Dark,
+    Light,
+}
+
+impl Theme {
+    const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light]; // All themes
+
+    // Human-readable name of the theme
+    fn label(self) -> &'static str {
+        match self {
+            Theme::System => "System",
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
         }
-        hasher.update(&buffer[..bytes_read]); // Update the hasher with the read bytes
     }
 
-    // Return the final hash in hexadecimal format
-    Ok(format!("{:x}", hasher.finalize()))
+    // Apply the theme to every following frame
+    fn apply(self, ctx: &egui::Context) {
+        ctx.set_theme(match self {
+            Theme::System => egui::ThemePreference::System,
+            Theme::Dark => egui::ThemePreference::Dark,
+            Theme::Light => egui::ThemePreference::Light,
+        });
+    }
 }
 
-// Function to find duplicate files in a directory (using multithreading)
-fn find_duplicates(
-    dir: &str,
-    progress: Arc<Mutex<f32>>,
-    stop_receiver: Arc<Mutex<mpsc::Receiver<()>>>,
-) -> io::Result<HashMap<String, Vec<String>>> {
-    // Collect all files in the directory and its subdirectories
-    let entries: Vec<_> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|entry| entry.file_type().is_file())
-        .collect();
+// Units available for the size filters
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum SizeUnit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+}
 
-    let total_files = entries.len(); // Total number of files to be processed
-    let file_map: Arc<Mutex<HashMap<String, Vec<String>>>> = Arc::new(Mutex::new(HashMap::new())); // To store hashes and their corresponding file paths
+impl SizeUnit {
+    const ALL: [SizeUnit; 4] = [SizeUnit::Bytes, SizeUnit::Kilobytes, SizeUnit::Megabytes, SizeUnit::Gigabytes]; // All units, smallest first
 
-    // Process each file in parallel
-    entries.par_iter().enumerate().for_each(|(i, entry)| {
-        // Check for a stop signal
-        if stop_receiver.lock().unwrap().try_recv().is_ok() {
-            return; // If a stop signal is received, exit
+    // Human-readable name of the unit
+    fn label(self) -> &'static str {
+        match self {
+            SizeUnit::Bytes => "B",
+            SizeUnit::Kilobytes => "KB",
+            SizeUnit::Megabytes => "MB",
+            SizeUnit::Gigabytes => "GB",
         }
+    }
 
-        let path = entry.path().to_path_buf(); // Get the path of the current entry
-        if let Ok(hash) = hash_file(&path) { // Hash the file
-            // Update progress
-            let mut progress = progress.lock().unwrap();
-            *progress = (i + 1) as f32 / total_files as f32; // Update progress percentage
+    // Convert an amount in this unit to bytes
+    fn to_bytes(self, amount: u64) -> u64 {
+        let multiplier = match self {
+            SizeUnit::Bytes => 1,
+            SizeUnit::Kilobytes => 1024,
+            SizeUnit::Megabytes => 1024 * 1024,
+            SizeUnit::Gigabytes => 1024 * 1024 * 1024,
+        };
+        amount.saturating_mul(multiplier)
+    }
+}
 
-            // Update the file_map with the hash and corresponding file path
-            let mut file_map = file_map.lock().unwrap();
-            file_map.entry(hash).or_insert_with(Vec::new).push(path.display().to_string());
+// Function to format a count with thousands separators, like 50,000
+fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(','); // Separate each group of three digits
         }
-    });
+        formatted.push(digit);
+    }
+    formatted
+}
+
+// Function to format a count compactly for tight spaces, like 950, 1.5k, 22k or 3.4M
+fn format_compact_count(count: usize) -> String {
+    match count {
+        0..=999 => count.to_string(),
+        1_000..=9_999 => format!("{:.1}k", count as f64 / 1e3),
+        10_000..=999_999 => format!("{}k", count / 1_000),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+// Function to format a duration as hours, minutes and seconds
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// Function to format the time a scan took, with tenths of a second for short scans
+fn format_elapsed(elapsed: Duration) -> String {
+    if elapsed.as_secs_f64() < 60.0 { format!("{:.1}s", elapsed.as_secs_f64()) } else { format_duration(elapsed) }
+}
+
+// Function to summarize a completed scan, like "Scanned 12,340 files in 8.2s (1,505 files/s, 1.2 GB read), found 312 duplicate groups."
+fn scan_summary(progress: &ScanProgress, elapsed: Duration, groups: usize) -> String {
+    let files = progress.files_found.load(Ordering::Relaxed);
+    let seconds = elapsed.as_secs_f64();
+    let time = format_elapsed(elapsed);
+    let rate = if seconds > 0.0 { (files as f64 / seconds).round() as usize } else { files };
+    let summary = format!(
+        "Scanned {} files in {} ({} files/s, {} read), found {} duplicate groups.",
+        format_count(files),
+        time,
+        format_count(rate),
+        format_size(progress.bytes_read.load(Ordering::Relaxed)),
+        format_count(groups),
+    );
+    // Listed with the other unreadable files, but worth a word of their own, as it points to a bug
+    match progress.panics.load(Ordering::Relaxed) {
+        0 => summary,
+        panics => format!("{} {} files caused an internal error and were skipped.", summary, format_count(panics)),
+    }
+}
+
+// Function to tell what the hash cache spared a scan, like "Reused the cached hashes of 12,000 files, re-hashed 40."
+fn cache_summary(stats: CacheStats) -> String {
+    format!("Reused the cached hashes of {} files, re-hashed {}.", format_count(stats.reused), format_count(stats.rehashed))
+}
+
+// Function to turn a number of days into a duration
+fn days(days: u64) -> Duration {
+    Duration::from_secs(days * 24 * 60 * 60)
+}
 
-    // Filter out the duplicates from the file_map
-    let duplicates = {
-        let file_map = file_map.lock().unwrap();
-        file_map.iter()
-            .filter(|(_, v)| v.len() > 1) // Keep only hashes with multiple files
-            .map(|(k, v)| (k.clone(), v.clone())) // Collect duplicates
-            .collect::<HashMap<_, _>>() // Collect as a HashMap
+// Function to shorten a path for display, keeping its end (the file name) visible
+fn truncate_path(path: &str, max_chars: usize) -> String {
+    let count = path.chars().count();
+    if count <= max_chars {
+        return path.to_string();
+    }
+    let tail: String = path.chars().skip(count - max_chars.saturating_sub(1)).collect();
+    format!("…{}", tail)
+}
+
+// Function to shorten a hash to its first `digits` characters for display, keeping the file name of split groups
+// Like "1f3a9c0b2d4e…" or "1f3a9c0b2d4e…:photo.jpg"; short hashes are left as they are
+fn short_hash(hash: &str, digits: usize) -> String {
+    let (digest, name) = match hash.split_once(':') {
+        Some((digest, name)) => (digest, Some(name)),
+        None => (hash, None),
     };
+    if digest.chars().count() <= digits {
+        return hash.to_string();
+    }
+    let short: String = digest.chars().take(digits).collect();
+    match name {
+        Some(name) => format!("{}…:{}", short, name),
+        None => format!("{}…", short),
+    }
+}
 
-    Ok(duplicates) // Return the duplicates
+// Name of the application, also naming the folder holding its settings, cache and log
+const APP_NAME: &str = "DupFinder";
+
+// Location of the hash cache, next to the saved settings
+fn hash_cache_path() -> Option<PathBuf> {
+    eframe::storage_dir(APP_NAME).map(|dir| dir.join("hash_cache.json"))
 }
 
-// Application structure for the UI to find duplicates
-struct DuplicateFinderApp {
-    dir_to_scan: String, // Directory selected for scanning
-    duplicates: Arc<Mutex<HashMap<String, Vec<String>>>>, // Map to hold duplicates
-    progress: Arc<Mutex<f32>>, // Progress of the scanning process
-    searching: bool, // Flag to indicate if a search is in progress
-    stop_sender: Option<mpsc::Sender<()>>, // Sender for stopping the search
-    stop_receiver: Arc<Mutex<mpsc::Receiver<()>>>, // Receiver for stopping the search
+// Default location of the log file, next to the saved settings
+fn default_log_path() -> Option<PathBuf> {
+    eframe::storage_dir(APP_NAME).map(|dir| dir.join("dupfinder.log"))
 }
 
-// Default implementation for the DuplicateFinderApp
-impl Default for DuplicateFinderApp {
-    fn default() -> Self {
-        let (stop_sender, stop_receiver) = mpsc::channel(); // Create a channel for stopping the process
-        Self {
-            dir_to_scan: String::new(), // Initialize directory to scan
-            duplicates: Arc::new(Mutex::new(HashMap::new())), // Initialize duplicates map
-            progress: Arc::new(Mutex::new(0.0)), // Initialize progress to 0
-            searching: false, // Searching is initially false
-            stop_sender: Some(stop_sender), // Store the sender for stopping the process
-            stop_receiver: Arc::new(Mutex::new(stop_receiver)), // Store the receiver for stopping the process
+// File receiving the log, if logging is enabled
+static LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+// Writer forwarding the log to LOG_FILE, so the log file can change while the app runs
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()), // Logging is disabled, drop the record
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match LOG_FILE.lock().unwrap().as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
         }
     }
 }
 
-// Implement the App trait for DuplicateFinderApp
-impl App for DuplicateFinderApp {
-    fn update(&mut self, ctx: &egui::Context, _: &mut Frame) {
-        // Central panel for UI elements
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.label("Select a directory to scan:"); // Label for directory selection
+// Function to install the logger, keeping only the records of this application
+// Logging stays off until a log file is set
+fn init_logging() {
+    let config = simplelog::ConfigBuilder::new()
+        .set_time_format_rfc3339()
+        .set_thread_level(log::LevelFilter::Off)
+        .set_target_level(log::LevelFilter::Off)
+        .add_filter_allow_str(module_path!()) // The GUI libraries log plenty of their own
+        .build();
+    let _ = simplelog::WriteLogger::init(log::LevelFilter::Debug, config, LogWriter);
+    log::set_max_level(log::LevelFilter::Off);
+}
 
-            // Button to choose a directory
-            if ui.button("Choose Directory").clicked() {
-                if let Some(path) = FileDialog::new().pick_folder() { // Open file dialog to pick a folder
-                    self.dir_to_scan = path.display().to_string(); // Update the directory to scan
-                    self.duplicates.lock().unwrap().clear(); // Clear previous duplicates
+// Function to start appending the log to `path`, or to stop logging with None
+fn set_log_file(path: Option<&Path>) -> io::Result<()> {
+    let file = match path {
+        Some(path) => {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            Some(fs::OpenOptions::new().create(true).append(true).open(path)?)
+        }
+        None => None,
+    };
+    let level = if file.is_some() { log::LevelFilter::Debug } else { log::LevelFilter::Off }; // Skip formatting records nobody reads
+    *LOG_FILE.lock().unwrap() = file;
+    log::set_max_level(level);
+    Ok(())
+}
+
+// Function to forget a deleted path in the duplicate folders, dropping folders that contained it
+fn remove_from_duplicate_dirs(groups: &mut Vec<DuplicateDirs>, path: &str) {
+    for group in groups.iter_mut() {
+        // Folders inside the path are gone, folders around it no longer match their copies
+        group.dirs.retain(|dir| !Path::new(dir).starts_with(path) && !Path::new(path).starts_with(dir));
+    }
+    groups.retain(|group| group.dirs.len() > 1);
+}
+
+// File formats the results can be exported to
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum ExportFormat {
+    Csv, // One row per file: hash,file_path,group_size
+    Json, // Array of { hash, size, files } objects
+    Html, // Self-contained report page with a table of the groups, for sharing
+}
+
+impl ExportFormat {
+    const ALL: [ExportFormat; 3] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Html]; // All formats
+
+    // Human-readable name of the format
+    fn label(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Html => "HTML",
+        }
+    }
+
+    // File extension used for the format
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+// A duplicate group as written to an export file
+#[derive(Serialize)]
+struct ExportGroup<'a> {
+    hash: &'a str, // Hash shared by all files in the group
+    size: u64, // Size of each file in bytes
+    files: Vec<&'a str>, // Paths of the duplicate files
+}
+
+// Function to collect duplicate groups for export, sorted by hash for stable output
+fn export_groups(duplicates: &HashMap<String, DuplicateGroup>) -> Vec<ExportGroup<'_>> {
+    let mut groups: Vec<ExportGroup> = duplicates
+        .iter()
+        .map(|(hash, group)| ExportGroup {
+            hash,
+            size: group.size,
+            files: group.files.iter().map(|file| file.path.as_str()).collect(),
+        })
+        .collect();
+    groups.sort_by(|a, b| a.hash.cmp(b.hash));
+    groups
+}
+
+// Function to quote a CSV field if it contains special characters
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\"")) // Escape quotes by doubling them
+    } else {
+        value.to_string()
+    }
+}
+
+// Function to escape text for HTML, both between tags and inside quoted attributes
+fn html_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Function to encode bytes as standard base64, for images embedded in a report
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |triple, (i, &byte)| triple | u32::from(byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('='); // Padding of a short last chunk
+            }
+        }
+    }
+    encoded
+}
+
+// Function to shrink an image to a thumbnail as a PNG data URL, None if it can't be decoded
+fn thumbnail_data_url(path: &str) -> Option<String> {
+    let image = image::open(extended_path(Path::new(path))).ok()?.thumbnail(THUMBNAIL_SIZE as u32, THUMBNAIL_SIZE as u32);
+    let mut png = io::Cursor::new(Vec::new());
+    image.write_to(&mut png, image::ImageFormat::Png).ok()?;
+    Some(format!("data:image/png;base64,{}", base64_encode(png.get_ref())))
+}
+
+// Style of the HTML report, inlined so the page works offline
+const HTML_STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+    table { border-collapse: collapse; width: 100%; } \
+    th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; vertical-align: top; } \
+    th { background: #eee; } td.number { text-align: right; white-space: nowrap; } \
+    ul { margin: 0; padding-left: 1.2em; } li { font-family: monospace; } \
+    img { display: block; margin: 2px 0; max-width: 64px; max-height: 64px; }";
+
+// Function to write duplicate groups as a self-contained HTML page, optionally with thumbnails of the images
+fn write_html(writer: &mut impl Write, groups: &[ExportGroup], thumbnails: bool) -> io::Result<()> {
+    let files: usize = groups.iter().map(|group| group.files.len()).sum();
+    let wasted: u64 = groups.iter().map(|group| group.size * group.files.len().saturating_sub(1) as u64).sum();
+    writeln!(writer, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{} report</title>", APP_NAME)?;
+    writeln!(writer, "<style>{}</style>\n</head>\n<body>\n<h1>Duplicate files</h1>", HTML_STYLE)?;
+    writeln!(writer, "<p>{} groups with {} files, {} of wasted space.</p>", format_count(groups.len()), format_count(files), format_size(wasted))?;
+    writeln!(writer, "<table>\n<tr><th>Hash</th><th>Files</th><th>Size each</th><th>Wasted</th><th>Paths</th></tr>")?;
+    for group in groups {
+        let wasted = group.size * group.files.len().saturating_sub(1) as u64;
+        write!(
+            writer,
+            "<tr><td title=\"{}\"><code>{}</code></td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td><ul>",
+            html_escape(group.hash),
+            html_escape(&short_hash(group.hash, 12)),
+            group.files.len(),
+            format_size(group.size),
+            format_size(wasted),
+        )?;
+        for file in &group.files {
+            let image = if thumbnails && is_image(Path::new(file)) { thumbnail_data_url(file) } else { None };
+            match image {
+                Some(url) => write!(writer, "<li><img src=\"{}\" alt=\"\">{}</li>", url, html_escape(file))?,
+                None => write!(writer, "<li>{}</li>", html_escape(file))?,
+            }
+        }
+        writeln!(writer, "</ul></td></tr>")?;
+    }
+    writeln!(writer, "</table>\n</body>\n</html>")
+}
+
+// Function to quote a path for a POSIX shell, where nothing is special inside single quotes but the quote itself
+fn shell_quote(path: &str) -> String {
+    format!("'{}'", path.replace('\'', "'\\''"))
+}
+
+// Function to quote a path for a Windows batch file, where percent signs have to be doubled even inside quotes
+fn batch_quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('%', "%%"))
+}
+
+// Function to write the files a deletion would remove, as a script of the platform's delete commands to review
+// and run by hand, or as a plain list of paths when the file name ends in .txt
+fn write_deletion_plan(path: &Path, files: &[String]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let list = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("txt"));
+    if list {
+        for file in files {
+            writeln!(writer, "{}", file)?;
+        }
+    } else if cfg!(windows) {
+        writeln!(writer, "@echo off\r\nrem Deletion plan written by {}: {} files, review before running\r\nchcp 65001 >nul\r", APP_NAME, files.len())?;
+        for file in files {
+            writeln!(writer, "del {}\r", batch_quote(file))?; // Files only, never folders
+        }
+    } else {
+        writeln!(writer, "#!/bin/sh\n# Deletion plan written by {}: {} files, review before running", APP_NAME, files.len())?;
+        for file in files {
+            writeln!(writer, "rm -- {}", shell_quote(file))?;
+        }
+    }
+    writer.flush()
+}
+
+// Function to write duplicate groups to a file in the given format
+fn write_export(path: &Path, format: ExportFormat, groups: &[ExportGroup], thumbnails: bool) -> io::Result<()> {
+    let writer = BufWriter::new(File::create(path)?); // Buffer writes to the output file
+    write_groups(writer, format, groups, thumbnails)
+}
+
+// Function to write duplicate groups to any writer in the given format
+// `thumbnails` embeds previews of the images in HTML reports, the other formats are plain data
+fn write_groups(mut writer: impl Write, format: ExportFormat, groups: &[ExportGroup], thumbnails: bool) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(writer, "{}", CSV_HEADER)?; // Header row
+            for group in groups {
+                for file in &group.files {
+                    writeln!(writer, "{},{},{}", csv_field(group.hash), csv_field(file), group.files.len())?; // Split groups' keys hold file names
                 }
             }
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, groups)?;
+            writeln!(writer)?; // End the file with a newline
+        }
+        ExportFormat::Html => write_html(&mut writer, groups, thumbnails)?,
+    }
+    writer.flush()
+}
 
-            ui.label(format!("Current Directory: {}", self.dir_to_scan)); // Display the selected directory
+// Header row of CSV exports, checked when one is read back
+const CSV_HEADER: &str = "hash,file_path,group_size";
 
-            // Button to start the search if conditions are met
-            if !self.dir_to_scan.is_empty() && !self.searching && ui.button("Start Search").clicked() {
-                self.searching = true; // Set searching flag to true
-                let dir_to_scan = self.dir_to_scan.clone(); // Clone the directory path
-                let progress = Arc::clone(&self.progress); // Clone the progress Arc
-                let duplicates = Arc::clone(&self.duplicates); // Clone the duplicates Arc
-                let stop_receiver = Arc::clone(&self.stop_receiver); // Clone the stop receiver Arc
+// A duplicate group read back from an earlier export
+#[derive(Deserialize)]
+struct ImportedGroup {
+    hash: String, // Hash the group was exported under
+    files: Vec<String>, // Paths of the duplicate files
+}
 
-                // Spawn a new thread for the search process
-                std::thread::spawn(move || {
-                    let found = find_duplicates(&dir_to_scan, progress, stop_receiver).unwrap_or_default(); // Find duplicates
-                    let mut duplicates = duplicates.lock().unwrap(); // Lock and update duplicates
-                    *duplicates = found; // Store found duplicates
-                });
+// Function to split CSV text into records, honoring quoted fields holding commas, doubled quotes or line breaks
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false; // Inside a quoted field
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"'); // Escaped quote
+                chars.next();
             }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field); // Last line without a line break
+        records.push(record);
+    }
+    records.retain(|record| record != &[""]); // Blank lines
+    records
+}
+
+// Function to read back the groups of a CSV or JSON export, told apart by their content
+// Anything that isn't laid out like the files `write_groups` produces is refused with the reason
+fn read_export(path: &Path) -> Result<Vec<ImportedGroup>, String> {
+    let text = fs::read_to_string(extended_path(path)).map_err(|e| e.to_string())?;
+    let text = text.trim_start_matches('\u{feff}'); // Byte order mark added by some editors
+    if text.trim_start().starts_with(['[', '{']) {
+        return serde_json::from_str(text).map_err(|e| format!("not a DupFinder JSON export ({})", e));
+    }
+    let mut records = csv_records(text).into_iter();
+    match records.next() {
+        Some(header) if header.join(",") == CSV_HEADER => {}
+        Some(header) => {
+            let header = header.join(",");
+            return Err(format!("unexpected CSV header \"{}\", DupFinder exports start with \"{}\"", truncate_path(&header, 80), CSV_HEADER));
+        }
+        None => return Err("the file is empty".to_string()),
+    }
+    let mut groups: Vec<ImportedGroup> = Vec::new(); // In file order
+    let mut index: HashMap<String, usize> = HashMap::new(); // Position of each hash in `groups`
+    for (i, record) in records.enumerate() {
+        let [hash, file, count] = record.as_slice() else {
+            return Err(format!("record {} has {} fields instead of 3", i + 1, record.len()));
+        };
+        if count.parse::<usize>().is_err() {
+            return Err(format!("record {} has \"{}\" as group size instead of a number", i + 1, count));
+        }
+        let position = *index.entry(hash.clone()).or_insert_with(|| {
+            groups.push(ImportedGroup { hash: hash.clone(), files: Vec::new() });
+            groups.len() - 1
+        });
+        groups[position].files.push(file.clone());
+    }
+    Ok(groups)
+}
+
+// An earlier export loaded to compare the current results against
+struct Baseline {
+    source: String, // File the groups were read from
+    groups: Vec<ImportedGroup>, // Groups of the earlier results
+}
+
+// How the current results differ from an earlier export, matched by hash
+struct ResultsComparison<'a> {
+    new: Vec<&'a DuplicateGroup>, // Groups that weren't there before
+    changed: Vec<&'a DuplicateGroup>, // Groups with the same hash whose files changed
+    resolved: Vec<&'a ImportedGroup>, // Earlier groups that are gone
+    unchanged: usize, // Groups with the same hash and files as before
+}
+
+// Function to compare the current results with the groups of an earlier export
+fn compare_results<'a>(current: &'a HashMap<String, DuplicateGroup>, previous: &'a [ImportedGroup]) -> ResultsComparison<'a> {
+    let previous_files: HashMap<&str, BTreeSet<&str>> =
+        previous.iter().map(|group| (group.hash.as_str(), group.files.iter().map(String::as_str).collect())).collect();
+    let mut comparison = ResultsComparison { new: Vec::new(), changed: Vec::new(), resolved: Vec::new(), unchanged: 0 };
+    for group in current.values() {
+        match previous_files.get(group.hash.as_str()) {
+            None => comparison.new.push(group),
+            Some(files) if group.files.iter().map(|file| file.path.as_str()).collect::<BTreeSet<_>>() == *files => comparison.unchanged += 1,
+            Some(_) => comparison.changed.push(group),
+        }
+    }
+    comparison.resolved = previous.iter().filter(|group| !current.contains_key(&group.hash)).collect();
+    comparison.new.sort_by(|a, b| a.hash.cmp(&b.hash));
+    comparison.changed.sort_by(|a, b| a.hash.cmp(&b.hash));
+    comparison
+}
+
+impl ResultsComparison<'_> {
+    // Whether no group matched by hash although both sides have some, as when another hash algorithm was used
+    fn disjoint(&self) -> bool {
+        self.changed.is_empty() && self.unchanged == 0 && !self.new.is_empty() && !self.resolved.is_empty()
+    }
+
+    // One-line count of each kind of difference
+    fn summary(&self) -> String {
+        format!(
+            "{} new, {} changed, {} resolved, {} unchanged groups",
+            self.new.len(),
+            self.changed.len(),
+            self.resolved.len(),
+            self.unchanged
+        )
+    }
+}
+
+// How a later export differs from an earlier one, matched by hash, as positions in their group lists
+struct SnapshotDiff {
+    added: Vec<usize>, // Groups of the newer export that the older one didn't have
+    changed: Vec<(usize, usize)>, // Groups in both with different files, in the older and the newer export
+    resolved: Vec<usize>, // Groups of the older export that are gone from the newer one
+    unchanged: usize, // Groups with the same hash and files in both
+}
+
+// Function to compare two exports, like the results of the same scan a month apart
+fn compare_snapshots(older: &[ImportedGroup], newer: &[ImportedGroup]) -> SnapshotDiff {
+    let files = |group: &ImportedGroup| group.files.iter().cloned().collect::<BTreeSet<String>>();
+    let older_index: HashMap<&str, usize> = older.iter().enumerate().map(|(i, group)| (group.hash.as_str(), i)).collect();
+    let newer_hashes: HashSet<&str> = newer.iter().map(|group| group.hash.as_str()).collect();
+    let mut diff = SnapshotDiff { added: Vec::new(), changed: Vec::new(), resolved: Vec::new(), unchanged: 0 };
+    for (i, group) in newer.iter().enumerate() {
+        match older_index.get(group.hash.as_str()) {
+            None => diff.added.push(i),
+            Some(&before) if files(&older[before]) == files(group) => diff.unchanged += 1,
+            Some(&before) => diff.changed.push((before, i)),
+        }
+    }
+    diff.resolved = (0..older.len()).filter(|&i| !newer_hashes.contains(older[i].hash.as_str())).collect();
+    diff
+}
+
+impl SnapshotDiff {
+    // Whether no group matched by hash although both exports have some, as when another hash algorithm was used
+    fn disjoint(&self) -> bool {
+        self.changed.is_empty() && self.unchanged == 0 && !self.added.is_empty() && !self.resolved.is_empty()
+    }
 
-            // Button to stop the search if it's in progress
-            if self.searching && ui.button("Stop Search").clicked() {
-                if let Some(sender) = &self.stop_sender {
-                    let _ = sender.send(()); // Send stop signal
-                    self.searching = false; // Immediately stop the search
-                    *self.progress.lock().unwrap() = 0.0; // Reset progress to 0
+    // One-line count of each kind of difference
+    fn summary(&self) -> String {
+        format!("{} added, {} changed, {} resolved, {} unchanged groups", self.added.len(), self.changed.len(), self.resolved.len(), self.unchanged)
+    }
+}
+
+// Two exports loaded side by side, only ever read
+#[derive(Default)]
+struct SnapshotView {
+    older: Option<Baseline>, // Export shown on the left
+    newer: Option<Baseline>, // Export shown on the right
+    diff: Option<SnapshotDiff>, // Differences, once both sides are loaded
+    error: Option<String>, // Why the last export couldn't be loaded
+}
+
+// Function to list groups of one side of a snapshot comparison, coloring the files the other side doesn't have
+fn show_snapshot_groups(ui: &mut egui::Ui, id: &str, label: &str, color: egui::Color32, groups: &[(&ImportedGroup, Option<&ImportedGroup>)], digits: usize) {
+    egui::CollapsingHeader::new(egui::RichText::new(format!("{} ({})", label, groups.len())).color(color)).id_salt(id).show(ui, |ui| {
+        egui::ScrollArea::vertical().id_salt(id).max_height(300.0).show(ui, |ui| {
+            for (group, other) in groups {
+                ui.strong(format!("{} ({} files)", short_hash(&group.hash, digits), group.files.len())).on_hover_text(&group.hash);
+                for file in &group.files {
+                    let text = egui::RichText::new(file).monospace();
+                    // Only the files that differ stand out in a changed group, every file does otherwise
+                    let differs = other.is_none_or(|other| !other.files.contains(file));
+                    ui.label(if differs { text.color(color) } else { text });
                 }
             }
+        });
+    });
+}
 
-            // Progress bar display
-            if self.searching {
-                ui.add(egui::ProgressBar::new(*self.progress.lock().unwrap()).animate(true)
-                    .desired_height(24.0)); // Increase height of the progress bar
-                if *self.progress.lock().unwrap() >= 1.0 {
-                    self.searching = false; // Stop searching if progress is complete
-                    *self.progress.lock().unwrap() = 0.0; // Reset progress
+// Orders in which duplicate groups can be listed
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum SortOrder {
+    GroupSize, // Most duplicates first
+    WastedSpace, // Most wasted space first
+    Path, // Alphabetically by the group's first path
+}
+
+impl SortOrder {
+    const ALL: [SortOrder; 3] = [SortOrder::GroupSize, SortOrder::WastedSpace, SortOrder::Path]; // All orders, in display order
+
+    // Human-readable name of the order
+    fn label(self) -> &'static str {
+        match self {
+            SortOrder::GroupSize => "Number of duplicates",
+            SortOrder::WastedSpace => "Wasted space",
+            SortOrder::Path => "File path",
+        }
+    }
+}
+
+// Function to list duplicate groups in the given order, breaking ties by hash for a stable display
+// Empty files always come last, apart from the real content duplicates
+fn sorted_groups(duplicates: &HashMap<String, DuplicateGroup>, order: SortOrder) -> Vec<(&String, &DuplicateGroup)> {
+    let mut groups: Vec<_> = duplicates.iter().collect();
+    match order {
+        SortOrder::GroupSize => groups.sort_by(|a, b| b.1.files.len().cmp(&a.1.files.len()).then_with(|| a.0.cmp(b.0))),
+        SortOrder::WastedSpace => groups.sort_by(|a, b| b.1.wasted_space().cmp(&a.1.wasted_space()).then_with(|| a.0.cmp(b.0))),
+        SortOrder::Path => groups.sort_by(|a, b| a.1.first_path().cmp(&b.1.first_path()).then_with(|| a.0.cmp(b.0))),
+    }
+    groups.sort_by_key(|(_, group)| group.is_empty_files()); // Stable, so the order above is kept otherwise
+    groups
+}
+
+// Function to pick every file except the newest (or oldest) one of each group
+// Files without a modification time count as the oldest
+fn select_all_but(duplicates: &HashMap<String, DuplicateGroup>, keep_newest: bool) -> HashSet<String> {
+    let mut selected = HashSet::new();
+    for group in duplicates.values().filter(|group| !group.approximate) { // Files of similar size are never picked automatically
+        let keep = if keep_newest {
+            group.files.iter().max_by_key(|file| file.modified)
+        } else {
+            group.files.iter().min_by_key(|file| file.modified)
+        };
+        let Some(keep) = keep else { continue };
+        let others = group.files.iter().filter(|file| file.path != keep.path && split_archive_path(&file.path).is_none()); // Archives are left alone
+        selected.extend(others.map(|file| file.path.clone()));
+    }
+    selected
+}
+
+// Function to pick every file except the one with the shortest (or longest) path of each group, often the canonical one
+// Ties go to the alphabetically first path; returns the selection with the file kept in each group
+fn select_by_path_length(duplicates: &HashMap<String, DuplicateGroup>, keep_shortest: bool) -> (HashSet<String>, HashMap<String, String>) {
+    let mut selected = HashSet::new();
+    let mut kept = HashMap::new();
+    for (hash, group) in duplicates.iter().filter(|(_, group)| !group.approximate) { // Files of similar size are never picked automatically
+        let length = |file: &FileEntry| file.path.chars().count();
+        let keep = group.files.iter().min_by(|a, b| {
+            let by_length = if keep_shortest { length(a).cmp(&length(b)) } else { length(b).cmp(&length(a)) };
+            by_length.then_with(|| a.path.cmp(&b.path))
+        });
+        let Some(keep) = keep else { continue };
+        let others = group.files.iter().filter(|file| file.path != keep.path && split_archive_path(&file.path).is_none()); // Archives are left alone
+        selected.extend(others.map(|file| file.path.clone()));
+        kept.insert(hash.clone(), keep.path.clone());
+    }
+    (selected, kept)
+}
+
+// Function to pick every file outside the given scanned directory, in the groups with a copy inside it
+// Every copy in that directory is kept, groups without one are left alone
+fn select_all_but_root(duplicates: &HashMap<String, DuplicateGroup>, root: usize) -> HashSet<String> {
+    let mut selected = HashSet::new();
+    for group in duplicates.values().filter(|group| !group.approximate && group.files.iter().any(|file| file.root == root)) {
+        let others = group.files.iter().filter(|file| file.root != root && split_archive_path(&file.path).is_none()); // Archives are left alone
+        selected.extend(others.map(|file| file.path.clone()));
+    }
+    selected
+}
+
+// Function to pick, in each group, every file outside the highest-priority scanned directory it has a copy in
+// `ranks` gives the priority of each root, lower first; every copy in the winning directory is kept, so groups
+// entirely inside one directory are left alone
+fn select_by_root_order(duplicates: &HashMap<String, DuplicateGroup>, ranks: &[usize]) -> HashSet<String> {
+    let rank = |file: &FileEntry| ranks.get(file.root).copied().unwrap_or(usize::MAX); // Roots no longer listed come last
+    let mut selected = HashSet::new();
+    for group in duplicates.values().filter(|group| !group.approximate) {
+        let Some(best) = group.files.iter().map(rank).min() else { continue };
+        let others = group.files.iter().filter(|file| rank(file) != best && split_archive_path(&file.path).is_none()); // Archives are left alone
+        selected.extend(others.map(|file| file.path.clone()));
+    }
+    selected
+}
+
+// Function to pick every file except the one matched by the highest-priority keep rule in each group
+// Rules are path globs, so `*/Photos/Originals/*` keeps the originals; groups no rule matches are left alone
+// Returns the selection together with the index of the rule that decided each group
+fn select_by_rules(duplicates: &HashMap<String, DuplicateGroup>, rules: &[String], case_sensitive: bool) -> (HashSet<String>, HashMap<String, usize>) {
+    let patterns: Vec<Option<Regex>> = rules.iter().map(|rule| parse_name_pattern(rule, PatternKind::Glob, case_sensitive).ok().flatten()).collect();
+    let mut selected = HashSet::new();
+    let mut matched = HashMap::new();
+    for (hash, group) in duplicates.iter().filter(|(_, group)| !group.approximate) {
+        // Paths are matched with forward slashes, so one rule works on every platform
+        let keep = patterns.iter().enumerate().find_map(|(i, pattern)| {
+            let pattern = pattern.as_ref()?;
+            group.files.iter().find(|file| pattern.is_match(&file.path.replace('\\', "/"))).map(|file| (i, file))
+        });
+        let Some((rule, keep)) = keep else { continue };
+        let others = group.files.iter().filter(|file| file.path != keep.path && split_archive_path(&file.path).is_none()); // Archives are left alone
+        selected.extend(others.map(|file| file.path.clone()));
+        matched.insert(hash.clone(), rule);
+    }
+    (selected, matched)
+}
+
+// Summary of the files currently marked for deletion
+struct Selection {
+    count: usize, // Number of selected files
+    size: u64, // Combined size of the selected files in bytes
+    emptied_groups: usize, // Groups in which every file is selected
+}
+
+// Function to summarize the selected files, counting the groups that would be left without a copy
+fn selection_summary(duplicates: &HashMap<String, DuplicateGroup>, selected: &HashSet<String>) -> Selection {
+    let mut selection = Selection { count: 0, size: 0, emptied_groups: 0 };
+    for group in duplicates.values() {
+        let count = group.files.iter().filter(|file| selected.contains(&file.path)).count();
+        selection.count += count;
+        selection.size += group.size * count as u64;
+        if count == group.files.len() {
+            selection.emptied_groups += 1; // Deleting these would lose the content entirely
+        }
+    }
+    selection
+}
+
+// Function to find the contents that removing the given files or folders would leave without any copy
+// Checked across every group, since groups split by file name share their content
+// Returns the key of each such content with the paths of its files
+fn emptied_contents(duplicates: &HashMap<String, DuplicateGroup>, paths: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut contents: BTreeMap<&str, (bool, Vec<String>)> = BTreeMap::new(); // Whether every file goes, and the files
+    for group in duplicates.values() {
+        let (emptied, files) = contents.entry(group.content.as_str()).or_insert((true, Vec::new()));
+        for file in &group.files {
+            *emptied &= paths.iter().any(|path| Path::new(&file.path).starts_with(path));
+            files.push(file.path.clone());
+        }
+    }
+    contents.into_iter().filter(|(_, (emptied, _))| *emptied).map(|(hash, (_, files))| (hash.to_string(), files)).collect()
+}
+
+// Function to describe contents that would be left without any copy, like "1f3a9c0b2d4e… (a.txt, b.txt)"
+fn describe_emptied(emptied: &[(String, Vec<String>)], digits: usize) -> String {
+    emptied.iter().map(|(hash, files)| format!("{} ({})", short_hash(hash, digits), files.join(", "))).collect::<Vec<_>>().join("\n")
+}
+
+// Duplicates of one file extension, for the statistics of the results
+struct ExtensionStats {
+    extension: String, // Lowercase extension, or "(none)"
+    groups: usize, // Duplicate groups of this kind of file
+    files: usize, // Files in those groups
+    wasted: u64, // Bytes taken up by the copies beyond the first of each group
+}
+
+// Function to add up the duplicates per file extension, the most wasted space first, from the sizes already known
+// A group counts under the extension most of its files have; similar sizes and empty files waste nothing certain
+fn extension_stats<'a>(groups: impl Iterator<Item = &'a DuplicateGroup>) -> Vec<ExtensionStats> {
+    let mut stats: HashMap<String, ExtensionStats> = HashMap::new();
+    for group in groups.filter(|group| !group.approximate && !group.is_empty_files()) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for file in &group.files {
+            let extension = Path::new(&file.path).extension().map_or_else(|| "(none)".to_string(), |ext| ext.to_string_lossy().to_lowercase());
+            *counts.entry(extension).or_default() += 1;
+        }
+        let Some((extension, _)) = counts.into_iter().max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0))) else { continue };
+        let entry = stats.entry(extension.clone()).or_insert(ExtensionStats { extension, groups: 0, files: 0, wasted: 0 });
+        entry.groups += 1;
+        entry.files += group.files.len();
+        entry.wasted += group.wasted_space();
+    }
+    let mut stats: Vec<ExtensionStats> = stats.into_values().collect();
+    stats.sort_by(|a, b| b.wasted.cmp(&a.wasted).then_with(|| a.extension.cmp(&b.extension)));
+    stats
+}
+
+// Ways of organizing the results
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+enum ResultsView {
+    ByHash, // One collapsible entry per duplicate group
+    ByFolder, // Duplicate files in a tree of their folders
+}
+
+// Folder of the "Group by folder" view, holding the duplicate files below it
+#[derive(Default)]
+struct FolderNode<'a> {
+    folders: BTreeMap<String, FolderNode<'a>>, // Subfolders by name
+    files: Vec<(&'a FileEntry, &'a DuplicateGroup)>, // Duplicate files directly in this folder, with their group
+    count: usize, // Duplicate files in this folder and all its subfolders
+}
+
+impl<'a> FolderNode<'a> {
+    // Build the folder tree of every file of the groups
+    fn build(groups: impl IntoIterator<Item = &'a DuplicateGroup>) -> Self {
+        let mut root = FolderNode::default();
+        for group in groups {
+            for file in &group.files {
+                let mut node = &mut root;
+                node.count += 1;
+                for component in Path::new(&file.path).parent().into_iter().flat_map(Path::components) {
+                    node = node.folders.entry(component.as_os_str().to_string_lossy().into_owned()).or_default();
+                    node.count += 1;
                 }
-            } else {
-                // If not searching, disable progress bar animation
-                ui.add(egui::ProgressBar::new(0.0).desired_height(24.0));
+                node.files.push((file, group));
             }
+        }
+        root
+    }
+}
 
-            // Display found duplicates
-            let duplicates_map = self.duplicates.lock().unwrap(); // Lock and retrieve duplicates map
-            if !duplicates_map.is_empty() {
-                ui.heading("Found Duplicates:"); // Heading for duplicates section
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    for (hash, files) in duplicates_map.iter() { // Iterate over found duplicates
-                        ui.collapsing(format!("Hash: {}", hash), |ui| {
-                            for file in files { // List each file under the corresponding hash
-                                ui.horizontal(|ui| {
-                                    ui.label(file); // Display file path
-                                });
-                            }
-                        });
-                    }
-                });
+// Function to show the subfolders and files of a folder, the most redundant subfolders first
+fn show_folder_tree(
+    ui: &mut egui::Ui,
+    node: &FolderNode,
+    path: &Path,
+    locked: bool, // Whether the results are still being found, so no file can be acted on
+    selected: &mut HashSet<String>,
+    error_message: &mut Option<String>,
+    to_ignore: &mut Option<Vec<String>>,
+) {
+    let mut folders: Vec<_> = node.folders.iter().collect();
+    folders.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(b.0)));
+    for (name, mut folder) in folders {
+        // Merge chains of folders holding nothing but a single subfolder into one entry
+        let mut folder_path = path.join(name);
+        while folder.files.is_empty() && folder.folders.len() == 1 {
+            let (name, subfolder) = folder.folders.iter().next().unwrap();
+            folder_path.push(name);
+            folder = subfolder;
+        }
+        let label = folder_path.strip_prefix(path).unwrap_or(&folder_path).display().to_string();
+        let header = format!("{} ({} duplicates)", label, folder.count);
+        egui::CollapsingHeader::new(header).id_salt(&folder_path).show(ui, |ui| {
+            show_folder_tree(ui, folder, &folder_path, locked, selected, error_message, to_ignore);
+        });
+    }
+
+    let mut files = node.files.clone();
+    files.sort_by(|a, b| a.0.path.cmp(&b.0.path));
+    for (file, group) in files {
+        ui.horizontal(|ui| {
+            // Checkbox marking the file for deletion, except for files inside archives
+            let deletable = split_archive_path(&file.path).is_none() && !locked;
+            let mut is_selected = selected.contains(&file.path);
+            if ui.add_enabled(deletable, egui::Checkbox::without_text(&mut is_selected))
+                .on_disabled_hover_text(if locked { "Available once the scan is done" } else { "Files inside archives can't be deleted" })
+                .changed()
+            {
+                if is_selected {
+                    selected.insert(file.path.clone());
+                } else {
+                    selected.remove(&file.path);
+                }
+            }
+            if ui.small_button("📂").on_hover_text("Open in folder").clicked() {
+                *error_message = reveal_in_file_manager(&file.path).err(); // Report a missing file
             }
+            if ui.add_enabled(!locked, egui::Button::new("🚫").small()).on_hover_text("Ignore this file in future scans").clicked() {
+                *to_ignore = Some(vec![file.path.clone()]);
+            }
+            let name = Path::new(&file.path).file_name().unwrap_or_default().to_string_lossy();
+            file_icon(ui, &file.path);
+            ui.label(name.as_ref());
+            // List the other copies on hover
+            let copies: Vec<&str> = group.files.iter().filter(|other| other.path != file.path).map(|other| other.path.as_str()).collect();
+            ui.weak(format!("{} other copies, {}", copies.len(), format_size(group.size))).on_hover_text(copies.join("\n"));
         });
     }
 }
 
-// Entry point for the application
-fn main() -> Result<(), eframe::Error> {
-    let app = DuplicateFinderApp::default(); // Create a new instance of the app
-    let native_options = eframe::NativeOptions::default(); // Default native options for the app
-    eframe::run_native(
-        "DupFinder", // Window title
-        native_options, // Native options
-        Box::new(|_| Ok(Box::new(app))), // Create the app instance
-    )
+// Function to remove a path, or every file inside a removed folder, from every duplicate group
+// Groups left with a single file are dropped
+fn remove_from_duplicates(duplicates: &mut HashMap<String, DuplicateGroup>, path: &str) {
+    for group in duplicates.values_mut() {
+        group.files.retain(|file| !Path::new(&file.path).starts_with(path)); // Forget the removed path
+    }
+    duplicates.retain(|_, group| group.files.len() > 1); // A single file is no longer a duplicate
+}
+
+// Function to tell why a file or folder about to be acted on no longer matches the scan, if it doesn't
+// Files are re-stated against the size and modification time they were found with, folders only have to exist
+fn stale_reason(path: &str, file: Option<&FileEntry>) -> Option<String> {
+    let unchanged = match file {
+        Some(file) => is_unchanged(file),
+        None => fs::metadata(extended_path(Path::new(path))).map(|_| true), // A duplicate folder
+    };
+    match unchanged {
+        Ok(true) => None,
+        Ok(false) => Some("modified since the scan".to_string()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Some("no longer exists".to_string()),
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+// Function to describe the files skipped for no longer matching the scan, like "Skipped a.txt: modified since the scan"
+fn stale_messages(stale: &[(String, String)]) -> Vec<String> {
+    stale.iter().map(|(path, reason)| format!("Skipped {}: {}", path, reason)).collect()
+}
+
+// Function to remove every ignored path from the duplicate groups, dropping groups left with a single file
+fn remove_ignored(duplicates: &mut HashMap<String, DuplicateGroup>, ignored: &BTreeSet<String>) {
+    for group in duplicates.values_mut() {
+        group.files.retain(|file| !ignored.contains(&file.path));
+    }
+    duplicates.retain(|_, group| group.files.len() > 1); // A single file is no longer a duplicate
+}
+
+// Function to move a file to the OS trash / recycle bin
+#[cfg(any(windows, target_os = "macos", all(unix, not(target_os = "ios"), not(target_os = "android"))))]
+fn move_to_trash(path: &str) -> Result<(), String> {
+    trash::delete(path).map_err(|e| e.to_string())
+}
+
+// Fallback for platforms without a supported trash implementation
+#[cfg(not(any(windows, target_os = "macos", all(unix, not(target_os = "ios"), not(target_os = "android")))))]
+fn move_to_trash(_path: &str) -> Result<(), String> {
+    Err("the Recycle Bin is not supported on this platform, use permanent deletion instead".to_string())
+}
+
+// Whether files can be taken back out of the trash, which the trash crate only supports on Windows and Linux
+const CAN_RESTORE: bool = cfg!(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))));
+
+// Function to move files back out of the trash to where they were, picking the copy trashed last (at or after `since`)
+#[cfg(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+fn restore_from_trash(paths: &[PathBuf], since: i64) -> Result<(), String> {
+    let items = trash::os_limited::list().map_err(|e| e.to_string())?;
+    let mut restore = Vec::new();
+    for path in paths {
+        let trashed = items.iter().filter(|item| item.original_path() == *path && item.time_deleted >= since);
+        match trashed.max_by_key(|item| item.time_deleted) {
+            Some(item) => restore.push(item.clone()),
+            None => return Err(format!("{} is no longer in the Recycle Bin", path.display())),
+        }
+    }
+    trash::os_limited::restore_all(restore).map_err(|e| e.to_string())
+}
+
+// Fallback for platforms where the trash can't be listed
+#[cfg(not(any(windows, all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+fn restore_from_trash(_paths: &[PathBuf], _since: i64) -> Result<(), String> {
+    Err("restoring from the Recycle Bin is not supported on this platform".to_string())
+}
+
+// Files sent to the Recycle Bin in one go, remembered so the deletion can be undone
+struct TrashedBatch {
+    paths: Vec<PathBuf>, // Absolute paths the files and folders were trashed from
+    groups: Vec<DuplicateGroup>, // Groups holding the trashed files, as they were before
+    trashed_at: i64, // Unix time just before the deletion, telling the files apart from older ones trashed from the same path
+}
+
+// Function to show a file in the OS file manager, selecting it where the platform supports it
+// Files inside an archive show the archive
+fn reveal_in_file_manager(path: &str) -> Result<(), String> {
+    let path = Path::new(containing_file(path));
+    if !path.exists() {
+        return Err(format!("{} no longer exists", path.display()));
+    }
+    #[cfg(windows)]
+    let mut command = {
+        use std::os::windows::process::CommandExt;
+        let mut command = std::process::Command::new("explorer");
+        command.raw_arg(format!("/select,\"{}\"", path.display())); // Explorer expects the quotes after the comma
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg("-R").arg(path);
+        command
+    };
+    // Other systems have no common way to select a file, so open its folder instead
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let mut command = {
+        let mut command = std::process::Command::new("xdg-open");
+        command.arg(path.parent().unwrap_or(path));
+        command
+    };
+    command.spawn().map(drop).map_err(|e| format!("Failed to open the file manager: {}", e))
+}
+
+// Function to check that two files are on the same filesystem, as hard links can't cross devices
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(fs::metadata(a)?.dev() == fs::metadata(b)?.dev())
+}
+
+// Other platforms report cross-device links when creating them
+#[cfg(not(unix))]
+fn same_filesystem(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(true)
+}
+
+// Function to replace `duplicate` with a hard link to `original`, so both paths share the same data
+// The link is created next to the duplicate first, so the duplicate is never lost if linking fails
+fn replace_with_hardlink(original: &str, duplicate: &str) -> Result<(), String> {
+    let (original, duplicate) = (Path::new(original), Path::new(duplicate));
+    if !same_filesystem(original, duplicate).map_err(|e| e.to_string())? {
+        return Err("the files are on different filesystems, hard links can't cross devices".to_string());
+    }
+    if !files_equal(&original.display().to_string(), &duplicate.display().to_string()).map_err(|e| e.to_string())? {
+        return Err("the contents differ from the kept file".to_string()); // Never replace a file that isn't an exact copy
+    }
+
+    let mut temp_name = duplicate.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".dupfinder-link");
+    let temp = duplicate.with_file_name(temp_name);
+    let (original, duplicate, temp) = (extended_path(original), extended_path(duplicate), extended_path(&temp)); // Deep paths too
+    fs::hard_link(&original, &temp).map_err(|e| e.to_string())?;
+    fs::rename(&temp, &duplicate).map_err(|e| {
+        let _ = fs::remove_file(&temp); // Don't leave the temporary link behind
+        e.to_string()
+    })
+}
+
+// Function to pick where a file moved into `dir` goes, adding a counter like "photo (2).jpg" when the name is taken
+fn unique_destination(dir: &Path, name: &std::ffi::OsStr) -> PathBuf {
+    let target = dir.join(name);
+    if !target.exists() {
+        return target;
+    }
+    let name = Path::new(name);
+    let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = name.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|counter| dir.join(format!("{} ({}){}", stem, counter, extension)))
+        .find(|target| !target.exists())
+        .unwrap() // Some counter is always free
+}
+
+// Function to open a file just to change its times, which read-only files allow too
+#[cfg(windows)]
+fn open_for_times(path: &Path) -> io::Result<File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    fs::OpenOptions::new().access_mode(0x100).open(path) // FILE_WRITE_ATTRIBUTES
+}
+
+#[cfg(not(windows))]
+fn open_for_times(path: &Path) -> io::Result<File> {
+    File::open(path) // The owner may change the times through any handle
+}
+
+// Function to copy a file, keeping its modification time so the copy still matches the scan, like a renamed file does
+fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    let modified = fs::metadata(from)?.modified();
+    fs::copy(from, to)?;
+    match modified {
+        Ok(modified) => open_for_times(to)?.set_modified(modified),
+        Err(_) => Ok(()), // Nothing to keep where the platform reports no modification time
+    }
+}
+
+// Function to move a file or folder, copying a file when it has to cross to another drive
+// The original is only removed once the copy is complete, and the copy is removed if either fails
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (extended_path(from), extended_path(to)); // Deep paths too
+    let Err(e) = fs::rename(&from, &to) else { return Ok(()) };
+    if !from.is_file() || to.exists() {
+        return Err(e); // Folders aren't copied across drives, and nothing is ever overwritten
+    }
+    copy_file(&from, &to).and_then(|()| fs::remove_file(&from)).inspect_err(|_| {
+        let _ = fs::remove_file(&to); // Leave a single copy, where it was
+    })
+}
+
+// Files moved to a folder in one go, remembered so they can be put back
+struct MovedBatch {
+    moves: Vec<(PathBuf, PathBuf)>, // Where each file was and where it went
+    groups: Vec<DuplicateGroup>, // Groups holding the moved files, as they were before
+}
+
+// Directories of the operating system on Unix, holding programs and settings rather than user files
+#[cfg(unix)]
+const SYSTEM_DIRS: [&str; 13] = ["/bin", "/boot", "/dev", "/etc", "/lib", "/lib32", "/lib64", "/proc", "/sbin", "/sys", "/usr", "/System", "/Library"];
+
+// Function to list the directories of the operating system, which are almost never meant to be scanned
+#[cfg(unix)]
+fn system_dirs() -> Vec<PathBuf> {
+    SYSTEM_DIRS.iter().map(PathBuf::from).collect()
+}
+
+#[cfg(windows)]
+fn system_dirs() -> Vec<PathBuf> {
+    let vars = ["SystemRoot", "ProgramFiles", "ProgramFiles(x86)", "ProgramData"];
+    vars.iter().filter_map(std::env::var_os).map(|dir| fs::canonicalize(&dir).unwrap_or_else(|_| PathBuf::from(dir))).collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn system_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+// Function to tell why scanning a directory is probably a mistake: it's a filesystem root like C:\ or /,
+// or holds the operating system; None for ordinary directories
+fn system_dir_warning(dir: &str) -> Option<&'static str> {
+    let path = fs::canonicalize(dir).unwrap_or_else(|_| PathBuf::from(dir)); // Resolves links and `..`
+    if path.parent().is_none() {
+        Some("the root of a drive or filesystem, so every file on it would be read")
+    } else if system_dirs().iter().any(|system| path.starts_with(system)) {
+        Some("a system directory with the files of the operating system")
+    } else {
+        None
+    }
+}
+
+// Function to read a directory typed or pasted in instead of picked with the dialog, which may be missing
+// Surrounding quotes, as copied by file managers, are dropped and a leading `~` stands for the home directory
+fn parse_typed_dir(text: &str) -> Result<PathBuf, String> {
+    let text = text.trim();
+    let text = text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).unwrap_or(text);
+    if text.is_empty() {
+        return Err("Type or paste the path of a directory".to_string());
+    }
+    let path = match (text.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(""), Some(home)) => PathBuf::from(home),
+        (Some(rest), Some(home)) if rest.starts_with(['/', '\\']) => PathBuf::from(home).join(&rest[1..]),
+        _ => PathBuf::from(text),
+    };
+    match fs::metadata(&path) {
+        Ok(metadata) if metadata.is_dir() => Ok(path),
+        Ok(_) => Err(format!("{} is a file, not a directory", path.display())),
+        Err(e) => Err(format!("Can't open {}: {}", path.display(), e)),
+    }
+}
+
+// Function to read a list of files to scan, one path per line like the output of find or fd; blank lines are skipped
+fn parse_file_list(text: &str) -> Vec<PathBuf> {
+    text.lines().map(|line| line.trim_end_matches('\r')).filter(|line| !line.trim().is_empty()).map(PathBuf::from).collect()
+}
+
+// Files waiting for the user to confirm their deletion
+#[derive(Clone)]
+struct PendingDelete {
+    paths: Vec<String>, // Files to delete
+    permanent: bool, // Delete permanently instead of sending to the Recycle Bin
+    folders: bool, // Whether the paths are whole folders
+}
+
+// Function to show a dropdown for choosing a size unit
+fn size_unit_picker(ui: &mut egui::Ui, id: &str, unit: &mut SizeUnit) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(unit.label())
+        .width(50.0)
+        .show_ui(ui, |ui| {
+            for option in SizeUnit::ALL {
+                ui.selectable_value(unit, option, option.label());
+            }
+        });
+}
+
+// Kind of file, told by its extension, for the icon shown next to it in the results
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum FileCategory {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Other,
+}
+
+impl FileCategory {
+    // Extensions of each category, lowercase
+    const IMAGES: [&str; 14] = ["jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "heic", "heif", "svg", "ico", "raw", "cr2"];
+    const VIDEOS: [&str; 10] = ["mp4", "mkv", "avi", "mov", "wmv", "webm", "flv", "m4v", "mpg", "mpeg"];
+    const AUDIO: [&str; 9] = ["mp3", "wav", "flac", "aac", "ogg", "m4a", "wma", "opus", "aiff"];
+    const DOCUMENTS: [&str; 14] = ["pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "txt", "md", "rtf", "csv"];
+    const ARCHIVES: [&str; 9] = ["zip", "rar", "7z", "tar", "gz", "bz2", "xz", "zst", "iso"];
+
+    // Category of a file by its extension, of the inner file for paths inside archives
+    fn of(path: &str) -> Self {
+        let extension = Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase()).unwrap_or_default();
+        let extension = extension.as_str();
+        if Self::IMAGES.contains(&extension) {
+            FileCategory::Image
+        } else if Self::VIDEOS.contains(&extension) {
+            FileCategory::Video
+        } else if Self::AUDIO.contains(&extension) {
+            FileCategory::Audio
+        } else if Self::DOCUMENTS.contains(&extension) {
+            FileCategory::Document
+        } else if Self::ARCHIVES.contains(&extension) {
+            FileCategory::Archive
+        } else {
+            FileCategory::Other
+        }
+    }
+
+    // Glyph shown for the category, all available in egui's built-in fonts
+    fn icon(self) -> &'static str {
+        match self {
+            FileCategory::Image => "🖼",
+            FileCategory::Video => "🎞",
+            FileCategory::Audio => "🎵",
+            FileCategory::Document => "📄",
+            FileCategory::Archive => "📦",
+            FileCategory::Other => "🗋",
+        }
+    }
+
+    // Human-readable name of the category
+    fn label(self) -> &'static str {
+        match self {
+            FileCategory::Image => "Image",
+            FileCategory::Video => "Video",
+            FileCategory::Audio => "Audio",
+            FileCategory::Document => "Document",
+            FileCategory::Archive => "Archive",
+            FileCategory::Other => "Other file",
+        }
+    }
+}
+
+// Function to show the icon of a file's category, naming the category on hover
+fn file_icon(ui: &mut egui::Ui, path: &str) {
+    let category = FileCategory::of(path);
+    ui.label(category.icon()).on_hover_text(category.label());
+}
+
+// Background colors of the source chips, one per scanned directory and repeating after the last
+const SOURCE_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(120, 180, 240), // Blue
+    egui::Color32::from_rgb(240, 170, 90), // Orange
+    egui::Color32::from_rgb(130, 210, 130), // Green
+    egui::Color32::from_rgb(220, 140, 200), // Pink
+    egui::Color32::from_rgb(230, 220, 110), // Yellow
+    egui::Color32::from_rgb(170, 170, 230), // Lavender
+];
+
+// Function to name a scanned directory briefly, by its last component, like "Photos" for /home/me/Photos
+// Roots like C:\ or network shares like \\server\share have none and keep their whole path
+fn source_label(dir: &str) -> String {
+    Path::new(dir).file_name().map_or_else(|| dir.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+// Function to show a colored chip telling which scanned directory a file was found under, like "2 Share"
+fn source_chip(ui: &mut egui::Ui, root: usize, dir: &str) {
+    egui::Frame::none()
+        .fill(SOURCE_COLORS[root % SOURCE_COLORS.len()])
+        .rounding(4.0)
+        .inner_margin(egui::Margin::symmetric(4.0, 0.0))
+        .show(ui, |ui| ui.label(egui::RichText::new(format!("{} {}", root + 1, source_label(dir))).small().color(egui::Color32::BLACK)))
+        .response
+        .on_hover_text(format!("Found under {}", dir));
+}
+
+// Size of the thumbnails shown next to image files, in points
+const THUMBNAIL_SIZE: f32 = 64.0;
+
+// Function to get the thumbnail texture of an image, decoding and caching it on first use
+fn thumbnail(ctx: &egui::Context, cache: &mut HashMap<String, Option<egui::TextureHandle>>, path: &str) -> Option<egui::TextureHandle> {
+    cache
+        .entry(path.to_string())
+        .or_insert_with(|| {
+            let image = image::open(extended_path(Path::new(path))).ok()?.thumbnail(THUMBNAIL_SIZE as u32, THUMBNAIL_SIZE as u32).to_rgba8(); // Decode and shrink once
+            let size = [image.width() as usize, image.height() as usize];
+            let color_image = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+            Some(ctx.load_texture(path, color_image, egui::TextureOptions::LINEAR))
+        })
+        .clone()
+}
+
+// Largest file compared line by line, bigger text files take too long to diff and display
+const TEXT_DIFF_MAX_SIZE: u64 = 4 * 1024 * 1024;
+
+// Lines of unchanged text shown around each change of a diff
+const TEXT_DIFF_CONTEXT: usize = 3;
+
+// Line-by-line comparison of two text files
+struct TextDiffView {
+    left: String, // Path of the old side
+    right: String, // Path of the new side
+    hunks: Vec<Vec<(ChangeTag, String)>>, // Changed lines with some context, empty if the files are identical
+}
+
+// Function to read a file as text, refusing binary files, which aren't valid UTF-8
+fn read_text(path: &str) -> Result<String, String> {
+    let bytes = with_reader(path, |reader, size| {
+        if size > TEXT_DIFF_MAX_SIZE {
+            return Err(io::Error::other(format!("larger than {}", format_size(TEXT_DIFF_MAX_SIZE))));
+        }
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    });
+    let bytes = bytes.map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    String::from_utf8(bytes).map_err(|_| format!("{} isn't a text file", path))
+}
+
+// Function to compare two text files line by line
+fn text_diff(left: &str, right: &str) -> Result<TextDiffView, String> {
+    let (old, new) = (read_text(left)?, read_text(right)?);
+    let diff = similar::TextDiff::from_lines(&old, &new);
+    let hunks = diff
+        .grouped_ops(TEXT_DIFF_CONTEXT)
+        .iter()
+        .map(|ops| ops.iter().flat_map(|op| diff.iter_changes(op)).map(|change| (change.tag(), change.to_string_lossy().into_owned())).collect())
+        .collect();
+    Ok(TextDiffView { left: left.to_string(), right: right.to_string(), hunks })
+}
+
+// Resource usage of the process, shown in the status bar to spot runaway growth on huge scans
+#[derive(Clone, Copy, Default)]
+struct ResourceUsage {
+    memory: Option<u64>, // Resident memory in bytes, if the platform reports it
+    handles: Option<usize>, // Open file descriptors or handles, if the platform reports them
+}
+
+// How long a toast stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+// How often the resource usage is sampled
+const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Function to sample the resource usage of the process, read from /proc on Linux
+#[cfg(target_os = "linux")]
+fn resource_usage() -> ResourceUsage {
+    let memory = fs::read_to_string("/proc/self/status").ok().and_then(|status| {
+        let line = status.lines().find(|line| line.starts_with("VmRSS:"))?; // Like "VmRSS:  123456 kB"
+        let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kilobytes * 1024)
+    });
+    let handles = fs::read_dir("/proc/self/fd").ok().map(|fds| fds.count());
+    ResourceUsage { memory, handles }
+}
+
+// Function to sample the resource usage of the process, its working set and handle count on Windows
+#[cfg(windows)]
+fn resource_usage() -> ResourceUsage {
+    use winapi::um::processthreadsapi::{GetCurrentProcess, GetProcessHandleCount};
+    use winapi::um::psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    unsafe {
+        let process = GetCurrentProcess();
+        let mut counters: PROCESS_MEMORY_COUNTERS = std::mem::zeroed();
+        let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        let memory = (GetProcessMemoryInfo(process, &mut counters, size) != 0).then_some(counters.WorkingSetSize as u64);
+        let mut handles: u32 = 0;
+        let handles = (GetProcessHandleCount(process, &mut handles) != 0).then_some(handles as usize);
+        ResourceUsage { memory, handles }
+    }
+}
+
+// Fallback for platforms where the resource usage isn't sampled
+#[cfg(not(any(target_os = "linux", windows)))]
+fn resource_usage() -> ResourceUsage {
+    ResourceUsage::default()
+}
+
+// Keyboard shortcuts of the main actions, Ctrl on Windows and Linux, Cmd on macOS
+const OPEN_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+const START_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+const ENTER_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Enter);
+const STOP_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Escape);
+const EXPORT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::E);
+
+// Messages sent from the search thread to the UI
+enum ScanMessage {
+    Found(String, u64, Vec<FileEntry>), // Copies found while the search runs: their hash, size and the files new to the group
+    Done(ScanOutcome), // The final results
+    Failed(String), // Why the search couldn't finish
+}
+
+// Results of a completed search
+struct ScanOutcome {
+    duplicates: HashMap<String, DuplicateGroup>, // Duplicate groups keyed by hash
+    verified: HashSet<String>, // Groups that were verified byte by byte
+    errors: ScanErrors, // Files that couldn't be read or hashed
+    compared: bool, // Whether only duplicates across folders were kept
+    primary_root: Option<usize>, // Index of the directory whose copies are kept, if one was marked
+    duplicate_dirs: Vec<DuplicateDirs>, // Folders with identical contents, if they were looked for
+    cache: Option<CacheStats>, // Files whose cached hashes were reused and files read again, if the cache was used
+}
+
+// Application structure for the UI to find duplicates
+// Settings are persisted between launches; runtime state is skipped
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct DuplicateFinderApp {
+    dirs_to_scan: Vec<String>, // Directories selected for scanning
+    primary_dir: Option<String>, // Directory whose copies are the originals to keep, if any
+    excluded_dirs: Vec<String>, // Directories inside the scanned ones that are skipped
+    hash_algo: HashAlgo, // Algorithm used for the next scan
+    hash_digits: usize, // Characters of each hash shown in the results, the full value being on hover
+    min_copies: usize, // Only groups with at least this many files are shown, without scanning again
+    #[serde(skip)]
+    results_title: String, // Heading describing how the current results were produced
+    #[serde(skip)]
+    duplicates: HashMap<String, DuplicateGroup>, // Map to hold duplicates
+    verify_bytes: bool, // Whether to verify duplicates byte by byte after hashing
+    use_hash_cache: bool, // Whether to reuse the hashes of unchanged files from previous scans
+    safe_mode: bool, // Whether deletions removing every copy of some content are refused
+    move_destination: String, // Folder the selected files were last moved to, offered again next time
+    image_similarity: bool, // Whether to look for visually similar images instead of identical files
+    similarity_threshold: u32, // Largest perceptual-hash distance (in bits) for images to count as similar
+    size_similarity: bool, // Whether to group files of similar size instead of identical files, without reading them
+    size_tolerance: f64, // Largest size difference in percent for files to count as similar
+    compare_folders: bool, // Whether to only report duplicates found in more than one of the directories
+    hide_first_folder: bool, // Whether to hide the copies in the first directory when comparing folders
+    find_duplicate_dirs: bool, // Whether to also look for folders with identical contents
+    match_names: bool, // Whether duplicates must also share the same file name
+    case_sensitive_names: bool, // Whether names differing only by case are different, for name patterns, matching names and keep rules
+    normalize_text: bool, // Whether text files are compared in normalized form instead of byte for byte
+    text_normalization: TextNormalization, // What the normalization of text files evens out
+    scan_archives: bool, // Whether to also scan the files inside zip archives
+    threads: usize, // Worker threads used by a scan, 0 for one per core
+    #[serde(skip)]
+    duplicate_dirs: Vec<DuplicateDirs>, // Folders with identical contents found by the last scan
+    #[serde(skip)]
+    compared: bool, // Whether the current results only hold duplicates across folders
+    #[serde(skip)]
+    primary_root: Option<usize>, // Index of the primary directory in the scan of the current results
+    #[serde(skip)]
+    result_roots: Vec<String>, // Directories the current results were scanned from, labelling where each file lives
+    #[serde(skip)]
+    verified: HashSet<String>, // Duplicate groups that were verified byte by byte
+    #[serde(skip)]
+    errors: ScanErrors, // Files that couldn't be read during the last scan
+    include_extensions: String, // Comma-separated extensions to scan, empty for all
+    exclude_extensions: String, // Comma-separated extensions to skip
+    name_pattern: String, // Pattern file names must match, empty for all
+    name_pattern_kind: PatternKind, // Syntax of `name_pattern`
+    skip_hidden: bool, // Whether to skip hidden files and folders
+    skip_protected: bool, // Whether to skip read-only files, and system files on Windows
+    skip_recent: bool, // Whether to skip files modified in the last `recent_days` days
+    recent_days: u32, // How many days a file counts as recently modified
+    pruned_dirs: String, // Comma-separated directory names that are never walked
+    symlinks: SymlinkPolicy, // How symbolic links are treated
+    reparse_points: ReparsePolicy, // How Windows reparse points that aren't links are treated
+    skip_empty: bool, // Whether to skip zero-byte files
+    min_size: u64, // Minimum file size in `min_size_unit` (inclusive)
+    min_size_unit: SizeUnit, // Unit of the minimum file size
+    limit_max_size: bool, // Whether the maximum file size applies
+    max_size: u64, // Maximum file size in `max_size_unit` (inclusive)
+    max_size_unit: SizeUnit, // Unit of the maximum file size
+    limit_depth: bool, // Whether the walk stops at `max_depth`
+    max_depth: usize, // Deepest level walked, 1 for only the files directly inside the directories
+    export_format: ExportFormat, // Format used when exporting results
+    export_thumbnails: bool, // Whether HTML reports embed thumbnails of the images
+    sort_order: SortOrder, // Order in which results are listed
+    results_view: ResultsView, // How results are organized
+    #[serde(skip)]
+    results_query: String, // Only groups with a path matching this are shown, empty for all
+    results_query_regex: bool, // Whether `results_query` is a regular expression
+    ignored_files: BTreeSet<String>, // Paths that are never reported, such as intentional copies
+    keep_rules: Vec<String>, // Path globs deciding which copy is kept, the first one taking priority
+    theme: Theme, // Appearance of the application
+    log_enabled: bool, // Whether scans and file operations are logged
+    log_path: String, // File the log is appended to
+    #[serde(skip)]
+    error_message: Option<String>, // Last error to show to the user
+    #[serde(skip)]
+    selected: HashSet<String>, // Paths marked for deletion
+    #[serde(skip)]
+    thumbnails: HashMap<String, Option<egui::TextureHandle>>, // Thumbnail textures by path, None if the image couldn't be decoded
+    #[serde(skip)]
+    pending_delete: Option<PendingDelete>, // Files waiting for the user to confirm their deletion
+    #[serde(skip)]
+    baseline: Option<Baseline>, // Earlier export the results are compared against, if one was imported
+    #[serde(skip)]
+    snapshots: Option<SnapshotView>, // Two exports compared with each other, while the window is open
+    #[serde(skip)]
+    toast: Option<(String, Instant)>, // Short confirmation shown at the bottom of the window, with when it appeared
+    #[serde(skip)]
+    pending_system_scan: Vec<(String, &'static str)>, // Directories to scan that need confirming first, with the reason
+    #[serde(skip)]
+    trash_history: Vec<TrashedBatch>, // Files sent to the Recycle Bin in this session, the last batch last
+    #[serde(skip)]
+    move_history: Vec<MovedBatch>, // Files moved to a folder in this session, the last batch last
+    #[serde(skip)]
+    new_keep_rule: String, // Keep rule being typed in
+    #[serde(skip)]
+    typed_dir: String, // Directory path being typed in, for systems where the folder dialog doesn't work
+    #[serde(skip)]
+    file_list: Option<Vec<PathBuf>>, // Files the current scan goes through instead of the directories, if any
+    #[serde(skip)]
+    file_list_text: String, // Paths pasted or loaded into the file list window, one per line
+    #[serde(skip)]
+    file_list_open: bool, // Whether the file list window is shown
+    #[serde(skip)]
+    typed_dir_error: Option<String>, // Why the typed path couldn't be added
+    #[serde(skip)]
+    kept_by_rule: HashMap<String, usize>, // Index of the keep rule that decided each group of the current selection
+    #[serde(skip)]
+    quick_scan: bool, // Whether the next search relies on the hash cache alone, without verifying byte by byte
+    #[serde(skip)]
+    group_heights: HashMap<String, f32>, // Height of each group when it was last shown, standing in for it out of view
+    #[serde(skip)]
+    kept_paths: HashMap<String, String>, // File kept in each group by the current selection by path length
+    #[serde(skip)]
+    text_diff: Option<TextDiffView>, // Comparison of two text files shown in its own window
+    #[serde(skip)]
+    progress: Arc<ScanProgress>, // Progress of the scanning process
+    #[serde(skip)]
+    scan_state: Arc<SharedScanState>, // Where the current search is, shared with its thread
+    #[serde(skip)]
+    scan_started: Option<Instant>, // When the current search started, for the ETA
+    #[serde(skip)]
+    scan_summary: Option<String>, // Statistics of the last completed search
+    #[serde(skip)]
+    nothing_found: Option<String>, // Explanation shown when the last completed search found no duplicates
+    #[serde(skip)]
+    paused_at: Option<Instant>, // When the current search was paused, if it is
+    #[serde(skip)]
+    pause: Arc<PauseFlag>, // Pause switch of the current search
+    #[serde(skip)]
+    scan_results: Option<mpsc::Receiver<ScanMessage>>, // Where the current search sends its results
+    #[serde(skip)]
+    live_pending: HashMap<String, DuplicateGroup>, // Copies found by the running search that aren't a group yet, once ignored files are left out
+    #[serde(skip)]
+    cancel: Arc<AtomicBool>, // Cancellation flag of the current search
+    #[serde(skip)]
+    resources: Option<(Instant, ResourceUsage)>, // Last resource usage sample, with when it was taken
+    #[serde(skip)]
+    window_title: String, // Title last given to the window, so it's only changed when needed
+}
+
+// Default implementation for the DuplicateFinderApp
+impl Default for DuplicateFinderApp {
+    fn default() -> Self {
+        Self {
+            dirs_to_scan: Vec::new(), // Initialize directories to scan
+            excluded_dirs: Vec::new(), // Nothing excluded by default
+            hash_algo: HashAlgo::Sha256, // SHA-256 by default
+            hash_digits: 12, // Enough to tell groups apart at a glance
+            min_copies: 2, // Every group by default
+            results_title: String::new(), // No results yet
+            duplicates: HashMap::new(), // Initialize duplicates map
+            verify_bytes: false, // Hash comparison only by default
+            use_hash_cache: true, // Repeat scans only read changed files
+            safe_mode: true, // One copy of everything always stays
+            move_destination: String::new(), // No folder picked yet
+            image_similarity: false, // Exact duplicates by default
+            similarity_threshold: 10, // Catches resized and re-encoded copies
+            size_similarity: false, // Exact duplicates by default
+            size_tolerance: 1.0, // Close enough for transcoded copies
+            compare_folders: false, // Report every duplicate by default
+            hide_first_folder: false, // Show every copy by default
+            find_duplicate_dirs: false, // Files only by default
+            match_names: false, // Content alone decides by default
+            case_sensitive_names: CASE_SENSITIVE_NAMES, // Like the filesystems of this platform
+            normalize_text: false, // Identical means identical bytes by default
+            text_normalization: TextNormalization::LineEndings, // The most common difference between copies of a script
+            scan_archives: false, // Reading archives costs extra CPU, so it's opt-in
+            threads: 0, // Use every core by default
+            duplicate_dirs: Vec::new(), // No results yet
+            compared: false, // No results yet
+            primary_dir: None, // Every directory is equal by default
+            primary_root: None,
+            result_roots: Vec::new(),
+            verified: HashSet::new(), // No verified groups yet
+            errors: Vec::new(), // No errors yet
+            include_extensions: String::new(), // Scan all extensions by default
+            exclude_extensions: String::new(), // Skip nothing by default
+            name_pattern: String::new(), // Match every name by default
+            name_pattern_kind: PatternKind::Glob, // Familiar wildcards by default
+            skip_hidden: false, // Scan hidden files by default
+            skip_protected: false, // Scan read-only files by default
+            skip_recent: false, // Scan files however new they are by default
+            recent_days: 7,
+            pruned_dirs: ".git, node_modules, target, $RECYCLE.BIN, System Volume Information".to_string(), // Skip common noise directories
+            symlinks: SymlinkPolicy::Skip, // Never scan the same file twice through a link
+            reparse_points: ReparsePolicy::Skip, // Never download cloud placeholders
+            skip_empty: true, // Empty files would otherwise form one huge meaningless group
+            min_size: 0, // No minimum size by default
+            min_size_unit: SizeUnit::Kilobytes,
+            limit_max_size: false, // No maximum size by default
+            max_size: 1, // Preset used once the maximum is enabled
+            limit_depth: false, // Unlimited depth by default
+            max_depth: 1, // Preset used once the depth is limited
+            max_size_unit: SizeUnit::Gigabytes,
+            export_format: ExportFormat::Csv, // CSV by default
+            export_thumbnails: true, // The report is for looking at
+            sort_order: SortOrder::WastedSpace, // Biggest wins first
+            results_view: ResultsView::ByHash, // One entry per set of identical files
+            results_query: String::new(), // Show every group
+            results_query_regex: false, // Plain text by default
+            ignored_files: BTreeSet::new(), // Nothing ignored by default
+            keep_rules: Vec::new(), // No rules by default
+            new_keep_rule: String::new(),
+            typed_dir: String::new(),
+            file_list: None,
+            file_list_text: String::new(),
+            file_list_open: false,
+            typed_dir_error: None,
+            kept_by_rule: HashMap::new(),
+            kept_paths: HashMap::new(),
+            group_heights: HashMap::new(),
+            quick_scan: false,
+            theme: Theme::System, // Match the rest of the desktop
+            log_enabled: false, // Logging is opt-in
+            log_path: default_log_path().map(|path| path.display().to_string()).unwrap_or_default(),
+            error_message: None, // No errors yet
+            selected: HashSet::new(), // Nothing selected yet
+            thumbnails: HashMap::new(), // Thumbnails are loaded on demand
+            pending_delete: None, // Nothing to delete yet
+            pending_system_scan: Vec::new(), // Nothing to confirm yet
+            toast: None, // No toast shown
+            baseline: None, // Nothing to compare against until an export is imported
+            snapshots: None, // Closed
+            trash_history: Vec::new(), // Nothing to undo yet
+            move_history: Vec::new(), // Nothing to put back yet
+            text_diff: None, // No comparison open
+            progress: Arc::new(ScanProgress::default()), // Initialize progress to 0
+            scan_state: Arc::default(), // No search is initially running
+            scan_started: None, // No search started yet
+            scan_summary: None, // No search completed yet
+            nothing_found: None, // No search completed yet
+            paused_at: None, // Not paused
+            pause: Arc::new(PauseFlag::default()), // Nothing to pause yet
+            scan_results: None, // No search to listen to
+            live_pending: HashMap::new(),
+            cancel: Arc::new(AtomicBool::new(false)), // Nothing to cancel yet
+            resources: None, // Sampled on the first frame
+            window_title: APP_NAME.to_string(), // Set when the window is created
+        }
+    }
+}
+
+impl DuplicateFinderApp {
+    // Create the app, restoring the settings saved by the previous launch
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        app.dirs_to_scan.retain(|dir| Path::new(dir).is_dir()); // Silently forget directories that no longer exist
+        app.theme.apply(&cc.egui_ctx); // Before the first frame, so the saved theme shows without a flash
+        app.apply_logging();
+        app
+    }
+
+    // Add a directory to scan, unless it's already in the list
+    fn add_directory(&mut self, path: &Path) {
+        let path = path.display().to_string();
+        if !self.dirs_to_scan.contains(&path) { // Don't scan the same directory twice
+            self.dirs_to_scan.push(path); // Add the directory to scan
+            self.clear_results(); // Clear previous results
+        }
+    }
+
+    // Add the folders dropped onto the window, using the parent folder of dropped files
+    fn add_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<PathBuf> = ctx.input(|input| input.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        for path in dropped {
+            let dir = if path.is_dir() { Some(path.as_path()) } else { path.parent() };
+            if let Some(dir) = dir {
+                self.add_directory(dir);
+            }
+        }
+
+        // Highlight the whole window as a drop zone while something is dragged over it
+        if ctx.input(|input| !input.raw.hovered_files.is_empty()) {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_zone")));
+            let screen = ctx.screen_rect();
+            painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+            painter.rect_stroke(screen.shrink(8.0), 8.0, egui::Stroke::new(3.0, egui::Color32::LIGHT_BLUE));
+            painter.text(
+                screen.center(),
+                egui::Align2::CENTER_CENTER,
+                "Drop folders to scan them",
+                egui::FontId::proportional(24.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
+    // Clear the results of the previous scan
+    fn clear_results(&mut self) {
+        self.duplicates.clear();
+        self.duplicate_dirs.clear();
+        self.verified.clear();
+        self.errors.clear();
+        self.selected.clear(); // Nothing left to select
+        self.kept_by_rule.clear();
+        self.kept_paths.clear();
+        self.scan_summary = None;
+        self.nothing_found = None;
+        self.thumbnails.clear(); // Free the cached textures
+        self.group_heights.clear();
+        self.live_pending.clear();
+    }
+
+    // Point the logger at the configured log file, or switch it off
+    fn apply_logging(&mut self) {
+        let path = self.log_enabled.then(|| PathBuf::from(self.log_path.trim()));
+        if let Err(e) = set_log_file(path.as_deref()) {
+            self.error_message = Some(format!("Failed to open the log file: {}", e));
+        }
+    }
+
+    // Build the scan filters from the current settings
+    fn scan_filter(&self) -> ScanFilter {
+        ScanFilter {
+            include_extensions: parse_extensions(&self.include_extensions),
+            exclude_extensions: parse_extensions(&self.exclude_extensions),
+            skip_hidden: self.skip_hidden,
+            skip_protected: self.skip_protected,
+            skip_recent: self.skip_recent.then(|| days(u64::from(self.recent_days))),
+            pruned_dirs: parse_list(&self.pruned_dirs),
+            excluded_dirs: self.excluded_dirs.iter().map(PathBuf::from).collect(),
+            min_size: self.min_size_unit.to_bytes(self.min_size).max(u64::from(self.skip_empty)), // Skipping empty files means at least 1 byte
+            max_size: self.limit_max_size.then(|| self.max_size_unit.to_bytes(self.max_size)),
+            max_depth: self.limit_depth.then_some(self.max_depth),
+            symlinks: self.symlinks,
+            reparse_points: self.reparse_points,
+            name_pattern: parse_name_pattern(&self.name_pattern, self.name_pattern_kind, self.case_sensitive_names).ok().flatten(), // Invalid patterns can't start a scan
+            archives: self.scan_archives && !self.image_similarity, // Images inside archives can't be decoded
+            file_list: self.file_list.clone(),
+        }
+    }
+
+    // Start a search, first asking for confirmation when a directory is a drive root or belongs to the system
+    fn request_search(&mut self, ctx: &egui::Context) {
+        self.pending_system_scan = self.dirs_to_scan
+            .iter()
+            .filter_map(|dir| system_dir_warning(dir).map(|reason| (dir.clone(), reason)))
+            .collect();
+        if self.pending_system_scan.is_empty() {
+            self.start_search(ctx);
+        }
+    }
+
+    // Start a search in the background with the current settings
+    fn start_search(&mut self, ctx: &egui::Context) {
+        self.clear_results(); // The new results stream in as they're found
+        self.scan_state = Arc::new(SharedScanState::new(ScanState::Scanning)); // Fresh state, so an old search finishing can't end this one
+        self.scan_started = Some(Instant::now()); // Start timing the scan
+        // Listed files don't come from any of the directories
+        let dirs = if self.file_list.is_some() { Vec::new() } else { self.dirs_to_scan.clone() };
+        self.result_roots = dirs.clone(); // The file roots index into these, whatever the list becomes
+        // Describe how the results were produced
+        self.results_title = if self.image_similarity {
+            format!("Found Similar Images (dHash, max distance {}):", self.similarity_threshold)
+        } else if self.size_similarity {
+            format!("Found Similar Sizes (approximate, within {}%):", self.size_tolerance)
+        } else if self.normalize_text {
+            format!("Found Duplicates ({}, text normalized: {}):", self.hash_algo.label(), self.text_normalization.label().to_lowercase())
+        } else {
+            format!("Found Duplicates ({}):", self.hash_algo.label())
+        };
+        let compare = self.compare_folders && dirs.len() > 1; // Comparing needs two folders
+        let quick = self.quick_scan; // Only files that changed are read again
+        let primary_root = self.primary_dir.as_ref().and_then(|primary| dirs.iter().position(|dir| dir == primary));
+        let scanner = DupScanner::new(dirs)
+            .algorithm(self.hash_algo)
+            .filter(self.scan_filter()) // Build the filters from the current settings
+            .threads(self.threads)
+            .verify(self.verify_bytes && !quick) // Verifying would read every copy again
+            .same_name(self.match_names)
+            .case_sensitive_names(self.case_sensitive_names)
+            .normalize_text(self.normalize_text.then_some(self.text_normalization))
+            .compare(compare)
+            .similar_images(self.image_similarity.then_some(self.similarity_threshold))
+            .similar_sizes((self.size_similarity && !self.image_similarity).then_some(self.size_tolerance))
+            .duplicate_dirs(self.find_duplicate_dirs)
+            .cache(if self.use_hash_cache || quick { hash_cache_path() } else { None }); // Where to keep the hashes, if anywhere
+        self.progress = scanner.progress(); // Fresh counters, so an old search can't skew them
+        self.cancel = scanner.cancel_flag(); // Fresh flag, so stopping an old search can't affect this one
+        self.pause = scanner.pause_flag(); // Fresh switch, the new search starts running
+        self.paused_at = None;
+        let (sender, receiver) = mpsc::channel(); // Channel carrying the results back to the UI
+        self.scan_results = Some(receiver);
+        let ctx = ctx.clone(); // Used to wake the UI up once the results are sent
+        let live = sender.clone();
+        let repaint = ctx.clone();
+        let state = self.scan_state.clone();
+        let scanner = scanner
+            .on_duplicates(move |hash, size, files| {
+                let _ = live.send(ScanMessage::Found(hash.to_string(), size, files.to_vec())); // Shown right away, but provisional
+            })
+            .on_progress(move |_| repaint.request_repaint()); // The counters are shared, the UI only has to redraw them
+
+        // Spawn a new thread for the search process
+        std::thread::spawn(move || {
+            let result = scanner.scan();
+            // Settle the race with the Stop button: whichever side moves the state first wins
+            if !state.finish() {
+                ctx.request_repaint(); // Stopped searches leave no partial results behind, only the stopping notice goes away
+                return;
+            }
+            let report = match result {
+                Ok(report) => report,
+                Err(e) => {
+                    let _ = sender.send(ScanMessage::Failed(e.to_string())); // Like a directory vanishing mid-scan
+                    ctx.request_repaint();
+                    return;
+                }
+            };
+            let duplicates: HashMap<String, DuplicateGroup> = report.groups.into_iter().map(|group| (group.hash.clone(), group)).collect();
+            let verified = if report.verified { duplicates.keys().cloned().collect() } else { HashSet::new() }; // Every remaining group is verified
+            // The UI may have stopped listening, in which case the results are simply dropped
+            let outcome = ScanOutcome { duplicates, verified, errors: report.errors, compared: compare, primary_root, duplicate_dirs: report.duplicate_dirs, cache: report.cache };
+            let _ = sender.send(ScanMessage::Done(outcome));
+            ctx.request_repaint();
+        });
+    }
+
+    // Pick up the copies found so far, and the results once the search is done
+    fn receive_scan_messages(&mut self) {
+        while let Some(receiver) = &self.scan_results {
+            match receiver.try_recv() {
+                Ok(ScanMessage::Found(hash, size, files)) => {
+                    // A group is only listed once it has two copies that aren't ignored
+                    let files = files.into_iter().filter(|file| !self.ignored_files.contains(&file.path));
+                    let mut group = self.duplicates.remove(&hash).or_else(|| self.live_pending.remove(&hash))
+                        .unwrap_or_else(|| DuplicateGroup::new(hash.clone(), size, Vec::new()));
+                    group.files.extend(files);
+                    if group.files.len() > 1 {
+                        self.duplicates.insert(hash, group);
+                    } else {
+                        self.live_pending.insert(hash, group);
+                    }
+                }
+                Ok(ScanMessage::Failed(e)) => {
+                    self.clear_results(); // Results of a failed search can't be trusted
+                    self.error_message = Some(format!("Scan aborted: {}", e));
+                    self.scan_state.set(ScanState::Idle);
+                    self.scan_results = None;
+                }
+                Ok(ScanMessage::Done(_)) if self.scan_state.get() != ScanState::Done => {
+                    self.scan_results = None; // Stopped after it completed, the results are dropped like any stopped search's
+                }
+                Ok(ScanMessage::Done(outcome)) => {
+                    self.clear_results(); // Forget the selection and thumbnails of the previous results
+                    self.duplicates = outcome.duplicates; // Store found duplicates
+                    remove_ignored(&mut self.duplicates, &self.ignored_files);
+                    self.verified = outcome.verified;
+                    self.compared = outcome.compared;
+                    self.primary_root = outcome.primary_root;
+                    if let Some(root) = self.primary_root {
+                        self.selected = select_all_but_root(&self.duplicates, root); // Preselect the copies outside the primary directory
+                    }
+                    self.duplicate_dirs = outcome.duplicate_dirs;
+                    self.scan_summary = self.scan_started.map(|started| scan_summary(&self.progress, started.elapsed(), self.duplicates.len()));
+                    if let (Some(summary), Some(stats)) = (&mut self.scan_summary, outcome.cache) {
+                        *summary = format!("{} {}", summary, cache_summary(stats)); // Shows what a quick rescan saved
+                    }
+                    self.errors = outcome.errors;
+                    // Tell a search that found nothing apart from one that hasn't run
+                    if self.duplicates.is_empty() {
+                        let files = format_count(self.progress.files_found.load(Ordering::Relaxed));
+                        let time = self.scan_started.map_or_else(String::new, |started| format!(" in {}", format_elapsed(started.elapsed())));
+                        let unreadable = if self.errors.is_empty() { String::new() } else { format!(", {} couldn't be read", format_count(self.errors.len())) };
+                        self.nothing_found = Some(format!("Scanned {} files{}{}", files, time, unreadable));
+                    }
+                    self.scan_results = None;
+                }
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.clear_results(); // The search ended without results, what it found so far is incomplete
+                    self.scan_state.set(ScanState::Idle);
+                    self.scan_results = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => break, // Still searching
+            }
+        }
+    }
+
+    // Delete or trash the given files, pruning them from the results and reporting failures
+    fn delete_files(&mut self, paths: &[String], permanent: bool) {
+        let (paths, stale) = self.stale_targets(paths);
+        let paths = &paths[..];
+        // Last line of defense, whatever the selection: nothing is deleted if some content would be gone for good
+        // Checked on the groups as scanned, with the skipped files as good as gone: the unchanged copies being deleted
+        // may be the last ones of their content; contents only the skipped files held don't hold the deletion up
+        let gone: Vec<String> = paths.iter().cloned().chain(stale.iter().map(|(path, _)| path.clone())).collect();
+        let emptied: Vec<(String, Vec<String>)> = emptied_contents(&self.duplicates, &gone)
+            .into_iter()
+            .filter(|(_, files)| files.iter().any(|file| paths.iter().any(|path| Path::new(file).starts_with(path))))
+            .collect();
+        if self.safe_mode && !emptied.is_empty() {
+            log::warn!("Refused to delete {} files, no copy of {} content(s) would be left", paths.len(), emptied.len());
+            let mut errors = stale_messages(&stale); // Still listed, nothing was done
+            errors.push(format!("Safe mode refused the deletion, no copy would be left of:\n{}", describe_emptied(&emptied, self.hash_digits)));
+            self.error_message = Some(errors.join("\n"));
+            return;
+        }
+        let mut errors = self.drop_stale(&stale);
+        // Remember the groups as they are, so trashed files can be listed again when the deletion is undone
+        let trashed_at = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_secs() as i64 - 1);
+        let affected = self.duplicates.values().filter(|group| group.files.iter().any(|file| paths.iter().any(|path| Path::new(&file.path).starts_with(path))));
+        let mut batch = TrashedBatch { paths: Vec::new(), groups: if permanent { Vec::new() } else { affected.cloned().collect() }, trashed_at };
+        for path in paths {
+            let result = if permanent && Path::new(path).is_dir() {
+                fs::remove_dir_all(extended_path(Path::new(path))).map_err(|e| e.to_string()) // A duplicate folder
+            } else if permanent {
+                fs::remove_file(extended_path(Path::new(path))).map_err(|e| e.to_string())
+            } else {
+                move_to_trash(path)
+            };
+            match result {
+                Ok(()) => {
+                    log::info!("{}: {}", if permanent { "Deleted permanently" } else { "Moved to the Recycle Bin" }, path);
+                    if !permanent {
+                        batch.paths.push(std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path))); // How the trash records it
+                    }
+                    remove_from_duplicates(&mut self.duplicates, path); // Update the results
+                    remove_from_duplicate_dirs(&mut self.duplicate_dirs, path);
+                    self.selected.retain(|selected| !Path::new(selected).starts_with(path)); // It can't be deleted twice
+                    self.thumbnails.retain(|thumbnail, _| !Path::new(thumbnail).starts_with(path)); // Free its thumbnail
+                }
+                Err(e) => {
+                    log::error!("Failed to delete {}: {}", path, e);
+                    errors.push(format!("Failed to delete {}: {}", path, e)) // Remember the failure
+                }
+            }
+        }
+        if !batch.paths.is_empty() {
+            self.trash_history.push(batch);
+        }
+        self.error_message = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+    }
+
+    // Re-check the files about to be deleted or moved, which other programs may have changed since the scan
+    // Returns the files still as they were found, and the others with why they're skipped; the results are left as
+    // they are, so the whole groups can still be checked before anything is done
+    fn stale_targets(&self, paths: &[String]) -> (Vec<String>, Vec<(String, String)>) {
+        let targets: HashSet<&String> = paths.iter().collect();
+        let found: HashMap<String, FileEntry> = self.duplicates
+            .values()
+            .flat_map(|group| &group.files)
+            .filter(|file| targets.contains(&file.path))
+            .map(|file| (file.path.clone(), file.clone()))
+            .collect(); // How each file was found, looked up once
+        let mut valid = Vec::new();
+        let mut stale = Vec::new();
+        for path in paths {
+            match stale_reason(path, found.get(path)) {
+                None => valid.push(path.clone()),
+                Some(reason) => stale.push((path.clone(), reason)),
+            }
+        }
+        (valid, stale)
+    }
+
+    // Take the skipped files out of the results, a rescan tells where they stand now
+    // Returns why each was skipped, to be reported
+    fn drop_stale(&mut self, stale: &[(String, String)]) -> Vec<String> {
+        for (path, reason) in stale {
+            log::warn!("Skipped {}: {}", path, reason);
+            remove_from_duplicates(&mut self.duplicates, path);
+            remove_from_duplicate_dirs(&mut self.duplicate_dirs, path);
+            self.selected.remove(path);
+        }
+        stale_messages(stale)
+    }
+
+    // Take the files of the last Recycle Bin batch back out of it, listing them in their groups again
+    fn undo_trash(&mut self) {
+        let Some(batch) = self.trash_history.pop() else { return };
+        if let Err(e) = restore_from_trash(&batch.paths, batch.trashed_at) {
+            log::error!("Failed to restore files from the Recycle Bin: {}", e);
+            self.error_message = Some(format!("Failed to restore from the Recycle Bin: {}", e));
+            return;
+        }
+        for path in &batch.paths {
+            log::info!("Restored from the Recycle Bin: {}", path.display());
+        }
+        self.relist_groups(batch.groups);
+        self.error_message = None;
+    }
+
+    // Move the given files into a folder, renaming them when a name is taken, and prune them from the results
+    fn move_files(&mut self, paths: &[String], dest: &Path) {
+        let (paths, stale) = self.stale_targets(paths);
+        let mut errors = self.drop_stale(&stale); // Moving loses no content, nothing to check first
+        // Remember the groups as they are, so moved files can be listed again once they're put back
+        let affected = self.duplicates.values().filter(|group| group.files.iter().any(|file| paths.iter().any(|path| Path::new(&file.path).starts_with(path))));
+        let mut batch = MovedBatch { moves: Vec::new(), groups: affected.cloned().collect() };
+        for path in &paths {
+            let from = std::path::absolute(path).unwrap_or_else(|_| PathBuf::from(path));
+            let result = match from.file_name() {
+                _ if split_archive_path(path).is_some() => Err("files inside an archive can't be moved".to_string()),
+                Some(name) => {
+                    let to = unique_destination(dest, name);
+                    move_path(&from, &to).map(|()| to).map_err(|e| e.to_string())
+                }
+                None => Err("not a file".to_string()),
+            };
+            match result {
+                Ok(to) => {
+                    log::info!("Moved {} to {}", path, to.display());
+                    batch.moves.push((from, to));
+                    remove_from_duplicates(&mut self.duplicates, path); // Update the results
+                    remove_from_duplicate_dirs(&mut self.duplicate_dirs, path);
+                    self.selected.retain(|selected| !Path::new(selected).starts_with(path));
+                    self.thumbnails.retain(|thumbnail, _| !Path::new(thumbnail).starts_with(path));
+                }
+                Err(e) => {
+                    log::error!("Failed to move {}: {}", path, e);
+                    errors.push(format!("Failed to move {}: {}", path, e)) // Remember the failure
+                }
+            }
+        }
+        if !batch.moves.is_empty() {
+            self.move_history.push(batch);
+        }
+        self.error_message = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+    }
+
+    // Put the files of the last moved batch back where they were, listing them in their groups again
+    // A file whose old place has been taken in the meantime stays in the folder
+    fn undo_move(&mut self) {
+        let Some(batch) = self.move_history.pop() else { return };
+        let mut errors = Vec::new();
+        for (from, to) in &batch.moves {
+            let result = if from.exists() {
+                Err("another file has taken its place".to_string())
+            } else {
+                move_path(to, from).map_err(|e| e.to_string())
+            };
+            match result {
+                Ok(()) => log::info!("Moved back {} to {}", to.display(), from.display()),
+                Err(e) => {
+                    log::error!("Failed to move back {}: {}", to.display(), e);
+                    errors.push(format!("Failed to move back {} to {}: {}", to.display(), from.display(), e))
+                }
+            }
+        }
+        self.relist_groups(batch.groups);
+        self.error_message = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+    }
+
+    // List groups again as they were before some of their files were taken away, without the copies that are
+    // gone or ignored since
+    fn relist_groups(&mut self, groups: Vec<DuplicateGroup>) {
+        for group in groups {
+            let files: Vec<FileEntry> = group
+                .files
+                .into_iter()
+                .filter(|file| Path::new(containing_file(&file.path)).exists() && !self.ignored_files.contains(&file.path))
+                .collect();
+            if files.len() > 1 {
+                self.duplicates.insert(group.hash.clone(), DuplicateGroup { files, ..group });
+            }
+        }
+    }
+
+    // Put text on the system clipboard, confirming it with a toast
+    fn copy_to_clipboard(&mut self, ctx: &egui::Context, text: String, message: String) {
+        ctx.copy_text(text);
+        self.toast = Some((message, Instant::now()));
+    }
+
+    // Never report these paths again, pruning them from the results so they can't be deleted either
+    fn ignore_files(&mut self, paths: &[String]) {
+        for path in paths {
+            self.selected.remove(path);
+            self.ignored_files.insert(path.clone());
+        }
+        remove_ignored(&mut self.duplicates, &self.ignored_files);
+    }
+
+    // Replace every file but the first with a hard link to it, pruning the replaced files from the results
+    fn hardlink_files(&mut self, paths: &[String]) {
+        let Some((original, duplicates)) = paths.split_first() else { return };
+        let mut errors = Vec::new();
+        for path in duplicates {
+            match replace_with_hardlink(original, path) {
+                Ok(()) => {
+                    log::info!("Replaced with a hard link to {}: {}", original, path);
+                    remove_from_duplicates(&mut self.duplicates, path); // No longer wastes any space
+                    self.selected.remove(path);
+                }
+                Err(e) => {
+                    log::error!("Failed to replace {} with a hard link: {}", path, e);
+                    errors.push(format!("Failed to replace {} with a hard link: {}", path, e)) // Remember the failure
+                }
+            }
+        }
+        self.error_message = if errors.is_empty() { None } else { Some(errors.join("\n")) };
+    }
+}
+
+// Implement the App trait for DuplicateFinderApp
+impl App for DuplicateFinderApp {
+    // Persist the settings between launches
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _: &mut Frame) {
+        self.add_dropped_files(ctx); // Folders dragged onto the window
+
+        // Keyboard shortcuts, only taken from the input when they apply so text fields keep their keys
+        let no_focus = ctx.memory(|memory| memory.focused().is_none()); // Enter belongs to a focused text field
+        let dialog_open = self.pending_delete.is_some() || !self.pending_system_scan.is_empty(); // Waiting for an answer
+        // A stopped search still winding down would save the hash cache alongside the new one
+        let can_start = !self.dirs_to_scan.is_empty() && !self.scan_state.is_busy() && !dialog_open;
+        let open_pressed = !dialog_open && ctx.input_mut(|input| input.consume_shortcut(&OPEN_SHORTCUT));
+        let start_pressed = can_start
+            && ctx.input_mut(|input| input.consume_shortcut(&START_SHORTCUT) || (no_focus && input.consume_shortcut(&ENTER_SHORTCUT)));
+        let stop_pressed = self.scan_state.is_scanning() && !dialog_open && ctx.input_mut(|input| input.consume_shortcut(&STOP_SHORTCUT));
+        let export_pressed = !self.duplicates.is_empty() && !self.scan_state.is_scanning() && !dialog_open && ctx.input_mut(|input| input.consume_shortcut(&EXPORT_SHORTCUT));
+
+        self.receive_scan_messages();
+
+        // Window title with the status, readable from the taskbar while the window is minimized
+        let title = if self.scan_state.is_scanning() && !self.progress.discovered.load(Ordering::Relaxed) {
+            format!("{} — discovering ({} files)", APP_NAME, format_compact_count(self.progress.files_found.load(Ordering::Relaxed)))
+        } else if self.scan_state.is_scanning() {
+            let processed = self.progress.processed.load(Ordering::Relaxed);
+            let total = self.progress.total.load(Ordering::Relaxed);
+            let counts = format!("{}/{}", format_compact_count(processed.min(total)), format_compact_count(total));
+            format!("{} — {:.0}% ({})", APP_NAME, self.progress.fraction() * 100.0, counts)
+        } else if !self.duplicates.is_empty() {
+            format!("{} — {} groups", APP_NAME, format_count(self.duplicates.len()))
+        } else {
+            APP_NAME.to_string()
+        };
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+
+        // Status bar with the progress counters and the resource usage, sampled at most once per interval
+        let stale = self.resources.is_none_or(|(sampled, _)| sampled.elapsed() >= RESOURCE_SAMPLE_INTERVAL);
+        if stale {
+            self.resources = Some((Instant::now(), resource_usage()));
+        }
+        if self.scan_state.is_scanning() {
+            ctx.request_repaint_after(RESOURCE_SAMPLE_INTERVAL); // Keep sampling while the scan runs
+        }
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let found = self.progress.files_found.load(Ordering::Relaxed);
+                let processed = self.progress.processed.load(Ordering::Relaxed);
+                ui.label(format!("Files found: {}", format_count(found)));
+                ui.separator();
+                ui.label(format!("Processed: {}", format_count(processed)));
+                let usage = self.resources.map(|(_, usage)| usage).unwrap_or_default();
+                ui.separator();
+                ui.label(format!("Memory: {}", usage.memory.map_or_else(|| "n/a".to_string(), format_size)));
+                ui.separator();
+                ui.label(format!("Open handles: {}", usage.handles.map_or_else(|| "n/a".to_string(), format_count)));
+            });
+        });
+
+        // Central panel for UI elements
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if dialog_open {
+                ui.disable(); // Nothing but the confirmation dialog reacts until it's answered
+            }
+            // Theme switcher
+            ui.horizontal(|ui| {
+                ui.label("Theme:");
+                for theme in Theme::ALL {
+                    if ui.selectable_value(&mut self.theme, theme, theme.label()).changed() {
+                        theme.apply(ctx);
+                    }
+                }
+            });
+
+            ui.label("Select directories to scan:"); // Label for directory selection
+
+            ui.horizontal(|ui| {
+                // Button to add a directory to the list
+                let hint = format!("Folders can also be dropped onto the window ({})", ctx.format_shortcut(&OPEN_SHORTCUT));
+                if ui.button("Add Directory").on_hover_text(hint).clicked() || open_pressed {
+                    if let Some(path) = FileDialog::new().pick_folder() { // Open file dialog to pick a folder
+                        self.add_directory(&path);
+                    }
+                }
+
+                // Button to skip a directory inside the scanned ones
+                if ui.button("Exclude Directory").on_hover_text("Skip a folder of intentional copies, like backups").clicked() {
+                    if let Some(path) = FileDialog::new().pick_folder() {
+                        let path = path.display().to_string();
+                        if !self.excluded_dirs.contains(&path) {
+                            self.excluded_dirs.push(path);
+                        }
+                    }
+                }
+
+                // Button to scan a given list of files instead, like the output of another tool
+                if ui.button("Scan File List...").on_hover_text("Check exactly the listed files, one path per line, like the output of find or fd").clicked() {
+                    self.file_list_open = true;
+                }
+
+                // Button to empty the directory list
+                if !self.dirs_to_scan.is_empty() && ui.button("Clear").clicked() {
+                    self.dirs_to_scan.clear(); // Remove all directories
+                    self.primary_dir = None;
+                    self.clear_results(); // Clear previous results
+                }
+            });
+
+            // Path typed or pasted in, for when the folder dialog doesn't show up without a desktop portal
+            ui.horizontal(|ui| {
+                ui.label("Or enter a path:");
+                let edit = ui.add(egui::TextEdit::singleline(&mut self.typed_dir).hint_text("/path/to/folder"));
+                if edit.changed() {
+                    self.typed_dir_error = None; // Only judge the path once it's submitted
+                }
+                let entered = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                if ui.add_enabled(!self.typed_dir.trim().is_empty(), egui::Button::new("Add")).clicked() || entered {
+                    match parse_typed_dir(&self.typed_dir) {
+                        Ok(path) => {
+                            self.add_directory(&path);
+                            self.typed_dir.clear();
+                            self.typed_dir_error = None;
+                        }
+                        Err(e) => self.typed_dir_error = Some(e),
+                    }
+                }
+            });
+            if let Some(e) = &self.typed_dir_error {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+
+            // Display the selected directories, each with its own remove button
+            // With several, they can be dragged into the order deciding which copies are preferred to keep
+            let mut to_remove = None;
+            let mut moved = None; // Directory dropped onto another one's place, as (from, to)
+            let mut primary = self.primary_dir.clone();
+            for (i, dir) in self.dirs_to_scan.iter().enumerate() {
+                let row = ui.horizontal(|ui| {
+                    if self.dirs_to_scan.len() > 1 {
+                        let handle = ui.add(egui::Label::new("☰").selectable(false).sense(egui::Sense::drag()))
+                            .on_hover_text("Drag to reorder, the copies in the directories higher up are preferred to keep");
+                        handle.dnd_set_drag_payload(i);
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove directory").clicked() {
+                        to_remove = Some(i); // Remember which directory to remove
+                    }
+                    // Mark the directory holding the originals, only meaningful with several directories
+                    if self.dirs_to_scan.len() > 1 {
+                        let is_primary = primary.as_ref() == Some(dir);
+                        if ui.selectable_label(is_primary, "⭐").on_hover_text("Primary: keep the copies in this directory, select the others").clicked() {
+                            primary = if is_primary { None } else { Some(dir.clone()) };
+                        }
+                    }
+                    ui.label(dir); // Display directory path
+                }).response;
+                // Show where a dragged directory would land, above the row when moving up and below it otherwise
+                if let Some(from) = row.dnd_hover_payload::<usize>().filter(|from| **from != i) {
+                    let y = if *from > i { row.rect.top() } else { row.rect.bottom() };
+                    ui.painter().hline(row.rect.x_range(), y, ui.visuals().selection.stroke);
+                }
+                if let Some(from) = row.dnd_release_payload::<usize>() {
+                    moved = Some((*from, i));
+                }
+            }
+            if let Some((from, to)) = moved {
+                let dir = self.dirs_to_scan.remove(from);
+                self.dirs_to_scan.insert(to, dir); // The results stay, the new order applies to the next selection
+            }
+            self.primary_dir = primary; // Takes effect with the next scan
+            if let Some(i) = to_remove {
+                let dir = self.dirs_to_scan.remove(i); // Remove the directory from the list
+                if self.primary_dir.as_ref() == Some(&dir) {
+                    self.primary_dir = None;
+                }
+                self.clear_results(); // Clear previous results
+            }
+
+            // Display the excluded directories, each with its own remove button
+            if !self.excluded_dirs.is_empty() {
+                ui.label("Excluded directories:");
+            }
+            let mut to_remove = None;
+            for (i, dir) in self.excluded_dirs.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Scan this directory again").clicked() {
+                        to_remove = Some(i); // Remember which exclusion to remove
+                    }
+                    ui.label(dir); // Display directory path
+                });
+            }
+            if let Some(i) = to_remove {
+                self.excluded_dirs.remove(i);
+            }
+
+            // Dropdown to choose the hashing algorithm
+            egui::ComboBox::from_label("Hash algorithm")
+                .selected_text(self.hash_algo.label())
+                .show_ui(ui, |ui| {
+                    for algo in HashAlgo::ALL {
+                        ui.selectable_value(&mut self.hash_algo, algo, algo.label());
+                    }
+                });
+
+            // Checkbox to enable byte-by-byte verification of hash matches
+            let exact = !self.image_similarity && !self.size_similarity; // Only exact duplicates are hashed
+            ui.add_enabled(exact, egui::Checkbox::new(&mut self.verify_bytes, "Verify byte-by-byte"))
+                .on_hover_text("Compare the contents of files with matching hashes to rule out collisions");
+
+            // Hash cache, skipping files that haven't changed since a previous scan
+            ui.horizontal(|ui| {
+                ui.add_enabled(exact, egui::Checkbox::new(&mut self.use_hash_cache, "Cache hashes between scans"))
+                    .on_hover_text("Reuse the hashes of files whose size and modification time haven't changed");
+                if let Some(path) = hash_cache_path().filter(|path| path.exists()) {
+                    if ui.button("Clear Cache").clicked() {
+                        self.error_message = fs::remove_file(&path).err().map(|e| format!("Failed to clear the hash cache: {}", e));
+                    }
+                }
+            });
+
+            // Worker threads, fewer leave room for other work and can be faster on network drives
+            ui.horizontal(|ui| {
+                ui.label("Worker threads:");
+                let cores = std::thread::available_parallelism().map_or(1, usize::from);
+                ui.add(egui::DragValue::new(&mut self.threads).range(0..=cores * 4).custom_formatter(|n, _| {
+                    if n == 0.0 { format!("all {} cores", cores) } else { n.to_string() }
+                }))
+                .on_hover_text("0 uses every core; fewer threads keep the machine responsive and can help on network drives");
+            });
+
+            // Image similarity mode, grouping visually similar images instead of identical files
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.image_similarity, "Image similarity")
+                    .on_hover_text("Group resized or re-encoded copies of the same image. Only decodable images are scanned.")
+                    .changed()
+                {
+                    self.size_similarity &= !self.image_similarity; // One grouping mode at a time
+                }
+                ui.add_enabled(self.image_similarity, egui::Slider::new(&mut self.similarity_threshold, 0..=32).text("max distance"));
+            });
+
+            // Similar size mode, a quick heuristic listing candidates like transcoded videos for a closer look
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut self.size_similarity, "Similar sizes (approximate)")
+                    .on_hover_text("Group files whose sizes differ by at most the given percentage, without reading them. \
+                                    The files aren't verified duplicates and are never selected automatically.")
+                    .changed()
+                {
+                    self.image_similarity &= !self.size_similarity;
+                }
+                ui.add_enabled(self.size_similarity, egui::DragValue::new(&mut self.size_tolerance).range(0.0..=50.0).speed(0.1).suffix(" %"));
+            });
+
+            // Folder comparison, answering "which files of the other folders already exist in the first one"
+            ui.add_enabled(self.dirs_to_scan.len() > 1, egui::Checkbox::new(&mut self.compare_folders, "Compare folders"))
+                .on_hover_text("Only report duplicates found in more than one of the selected directories")
+                .on_disabled_hover_text("Add at least two directories to compare them");
+            ui.checkbox(&mut self.match_names, "Require matching file names").on_hover_text(
+                "Only report files with the same content and the same name, like \"photo.jpg\" in two folders. \
+                 Identical files named differently are left out, so fewer duplicates are found.",
+            );
+            // Independent of the filesystem, for content coming from another platform
+            ui.checkbox(&mut self.case_sensitive_names, "Case-sensitive file names").on_hover_text(
+                "Whether names differing only by case, like \"IMG.JPG\" and \"img.jpg\", are different for the file name pattern, \
+                 matching file names and keep rules. The default follows this system.",
+            );
+            // Text normalization, changing what identical means for text files only
+            ui.add_enabled_ui(exact, |ui| ui.horizontal(|ui| {
+                ui.checkbox(&mut self.normalize_text, "Normalize text files").on_hover_text(
+                    "Treat UTF-8 text files with a known extension (.txt, .sh, .py, ...) as duplicates when they only differ in \
+                     formatting. Binary files are always compared byte for byte.",
+                );
+                ui.add_enabled_ui(self.normalize_text, |ui| {
+                    egui::ComboBox::from_id_salt("text_normalization")
+                        .selected_text(self.text_normalization.label())
+                        .show_ui(ui, |ui| {
+                            for normalization in TextNormalization::ALL {
+                                ui.selectable_value(&mut self.text_normalization, normalization, normalization.label());
+                            }
+                        });
+                });
+            }));
+            ui.add_enabled(exact, egui::Checkbox::new(&mut self.find_duplicate_dirs, "Find duplicate folders"))
+                .on_hover_text("Also report folders whose files are all identical copies of another folder's")
+                .on_disabled_hover_text("Similar files aren't identical, so folders can't be compared");
+            ui.add_enabled(!self.image_similarity, egui::Checkbox::new(&mut self.scan_archives, "Look inside zip archives"))
+                .on_hover_text("Also compare the files inside .zip archives, listed as archive.zip::file. They can't be deleted from there.")
+                .on_disabled_hover_text("Images inside archives can't be compared");
+
+            // Filters applied while collecting files
+            let pattern_error = parse_name_pattern(&self.name_pattern, self.name_pattern_kind, self.case_sensitive_names).err();
+            ui.collapsing("Filters", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Include extensions:");
+                    ui.add(egui::TextEdit::singleline(&mut self.include_extensions).hint_text("jpg, png, raw (empty for all)"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Exclude extensions:");
+                    ui.add(egui::TextEdit::singleline(&mut self.exclude_extensions).hint_text("tmp, log"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("File name matches:");
+                    ui.add(egui::TextEdit::singleline(&mut self.name_pattern).hint_text("*.bak or IMG_\\d+ (empty for all)"));
+                    egui::ComboBox::from_id_salt("name_pattern_kind")
+                        .selected_text(self.name_pattern_kind.label())
+                        .width(60.0)
+                        .show_ui(ui, |ui| {
+                            for kind in [PatternKind::Glob, PatternKind::Regex] {
+                                ui.selectable_value(&mut self.name_pattern_kind, kind, kind.label());
+                            }
+                        });
+                });
+                if let Some(e) = &pattern_error {
+                    ui.colored_label(egui::Color32::RED, format!("Invalid pattern: {}", e)); // Shown inline, the scan can't start
+                }
+                ui.checkbox(&mut self.skip_hidden, "Skip hidden files/folders");
+                let protected = if cfg!(windows) { "Skip read-only and system files" } else { "Skip read-only files" };
+                ui.checkbox(&mut self.skip_protected, protected)
+                    .on_hover_text("Files protected on purpose are left out of the results, so they're never deleted");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.skip_recent, "Skip files modified in the last")
+                        .on_hover_text("Files still being worked on are left out of the results, so they're never deleted");
+                    ui.add_enabled(self.skip_recent, egui::DragValue::new(&mut self.recent_days).range(1..=u32::MAX));
+                    ui.label("days");
+                });
+
+                // Size bounds, both inclusive
+                ui.checkbox(&mut self.skip_empty, "Skip empty files (min size > 0)")
+                    .on_hover_text("When included, zero-byte files are listed in a separate \"Empty files\" group");
+                ui.horizontal(|ui| {
+                    ui.label("Min size (inclusive):");
+                    ui.add(egui::DragValue::new(&mut self.min_size));
+                    size_unit_picker(ui, "min_size_unit", &mut self.min_size_unit);
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.limit_max_size, "Max size (inclusive):");
+                    ui.add_enabled(self.limit_max_size, egui::DragValue::new(&mut self.max_size));
+                    ui.add_enabled_ui(self.limit_max_size, |ui| {
+                        size_unit_picker(ui, "max_size_unit", &mut self.max_size_unit);
+                    });
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.limit_depth, "Max depth:")
+                        .on_hover_text("1 only scans the files directly inside the selected directories, 2 their subfolders too, and so on");
+                    ui.add_enabled(self.limit_depth, egui::DragValue::new(&mut self.max_depth).range(1..=usize::MAX));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Skip directories named:");
+                    ui.add(egui::TextEdit::singleline(&mut self.pruned_dirs).hint_text(".git, node_modules, target"));
+                });
+                egui::ComboBox::from_label("Symbolic links")
+                    .selected_text(self.symlinks.label())
+                    .show_ui(ui, |ui| {
+                        for policy in SymlinkPolicy::ALL {
+                            ui.selectable_value(&mut self.symlinks, policy, policy.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Following links may find the same file twice; link loops are reported as errors. \
+                                    On Windows, junctions count as links too.");
+                if cfg!(windows) {
+                    egui::ComboBox::from_label("Reparse points")
+                        .selected_text(self.reparse_points.label())
+                        .show_ui(ui, |ui| {
+                            for policy in ReparsePolicy::ALL {
+                                ui.selectable_value(&mut self.reparse_points, policy, policy.label());
+                            }
+                        })
+                        .response
+                        .on_hover_text("Files and folders like OneDrive placeholders, which reading would download. \
+                                        Junctions and symbolic links follow the link setting instead.");
+                }
+            });
+
+            // Optional log of everything the app does, for auditing deletions
+            let mut log_changed = false;
+            ui.collapsing("Log", |ui| {
+                log_changed |= ui.checkbox(&mut self.log_enabled, "Log scans and file operations").changed();
+                ui.horizontal(|ui| {
+                    ui.label("Log file:");
+                    log_changed |= ui.text_edit_singleline(&mut self.log_path).lost_focus();
+                    if ui.button("Browse...").clicked() {
+                        if let Some(path) = FileDialog::new().set_file_name("dupfinder.log").save_file() {
+                            self.log_path = path.display().to_string();
+                            log_changed = true;
+                        }
+                    }
+                });
+            });
+            if log_changed {
+                self.apply_logging(); // Switch to the new settings right away
+            }
+
+            // Files ignored from the results, each with its own button to report it again
+            ui.collapsing(format!("Ignored files ({})", self.ignored_files.len()), |ui| {
+                if self.ignored_files.is_empty() {
+                    ui.weak("Files ignored from the results are listed here");
+                    return;
+                }
+                let mut to_remove = None;
+                egui::ScrollArea::vertical().id_salt("ignored_files").max_height(150.0).show(ui, |ui| {
+                    for path in &self.ignored_files {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").on_hover_text("Report this file again from the next scan").clicked() {
+                                to_remove = Some(path.clone());
+                            }
+                            ui.label(path);
+                        });
+                    }
+                });
+                if let Some(path) = to_remove {
+                    self.ignored_files.remove(&path);
+                }
+                if ui.button("Clear").clicked() {
+                    self.ignored_files.clear();
+                }
+            });
+
+            // Path globs deciding which copy of each group is kept, in priority order
+            ui.collapsing(format!("Keep rules ({})", self.keep_rules.len()), |ui| {
+                ui.weak("The copy matching the first rule is kept, like */Photos/Originals/*; groups no rule matches are left alone");
+                let mut action = None; // (index, move by) where a move of 0 removes the rule
+                for (i, rule) in self.keep_rules.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✖").on_hover_text("Remove rule").clicked() {
+                            action = Some((i, 0));
+                        }
+                        if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).on_hover_text("Higher priority").clicked() {
+                            action = Some((i, -1));
+                        }
+                        if ui.add_enabled(i + 1 < self.keep_rules.len(), egui::Button::new("⬇").small()).on_hover_text("Lower priority").clicked() {
+                            action = Some((i, 1));
+                        }
+                        ui.label(format!("{}. {}", i + 1, rule));
+                    });
+                }
+                match action {
+                    Some((i, 0)) => {
+                        self.keep_rules.remove(i);
+                    }
+                    Some((i, step)) => self.keep_rules.swap(i, i.saturating_add_signed(step)),
+                    None => {}
+                }
+                if action.is_some() {
+                    self.kept_by_rule.clear(); // The rule numbers changed
+                }
+                ui.horizontal(|ui| {
+                    let edit = ui.add(egui::TextEdit::singleline(&mut self.new_keep_rule).hint_text("*/Originals/*"));
+                    let entered = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                    let rule = self.new_keep_rule.trim().to_string();
+                    if (ui.add_enabled(!rule.is_empty(), egui::Button::new("Add")).clicked() || entered) && !rule.is_empty() {
+                        if !self.keep_rules.contains(&rule) {
+                            self.keep_rules.push(rule);
+                        }
+                        self.new_keep_rule.clear();
+                    }
+                });
+            });
+
+            // Buttons to start the search, or to run the last one again, if conditions are met
+            if can_start {
+                ui.horizontal(|ui| {
+                    let start = ui.add_enabled(pattern_error.is_none(), egui::Button::new("Start Search"))
+                        .on_hover_text(format!("{} or {}", ctx.format_shortcut(&ENTER_SHORTCUT), ctx.format_shortcut(&START_SHORTCUT)))
+                        .on_disabled_hover_text("Fix the file name pattern in the filters first")
+                        .clicked()
+                        || (start_pressed && pattern_error.is_none());
+                    // Handy after deleting some duplicates, to confirm what's left
+                    let rescan = !self.results_title.is_empty()
+                        && ui.add_enabled(pattern_error.is_none(), egui::Button::new("Rescan"))
+                            .on_hover_text("Clear the results and scan the same directories again")
+                            .clicked();
+                    // Cheap enough to run often, only new and changed files are read, the others keep their cached hashes
+                    let exact = !self.image_similarity && !self.size_similarity; // Similar files aren't hashed
+                    let quick = !self.results_title.is_empty()
+                        && ui.add_enabled(pattern_error.is_none() && exact, egui::Button::new("Quick Rescan"))
+                            .on_hover_text("Scan again, only hashing the files whose size or modification time changed, without verifying byte by byte")
+                            .on_disabled_hover_text("Only exact duplicates can be looked up in the hash cache")
+                            .clicked();
+                    if start {
+                        self.file_list = None; // Back to walking the directories
+                    }
+                    if start || rescan || quick {
+                        self.quick_scan = quick;
+                        self.request_search(ctx); // Clears the results once the scan starts
+                    }
+                });
+            }
+
+            // Buttons to pause, resume or stop the search if it's in progress
+            if self.scan_state.is_scanning() {
+                ui.horizontal(|ui| {
+                    if let Some(paused_at) = self.paused_at {
+                        if ui.button("Resume").clicked() {
+                            self.pause.set(false); // Wake the workers up
+                            self.paused_at = None;
+                            if let Some(started) = &mut self.scan_started {
+                                *started += paused_at.elapsed(); // Leave the pause out of the ETA
+                            }
+                        }
+                    } else if ui.button("Pause").on_hover_text("Free up disk I/O, keeping the progress made so far").clicked() {
+                        self.pause.set(true); // Workers wait before their next file
+                        self.paused_at = Some(Instant::now());
+                    }
+                    if ui.button("Stop Search").on_hover_text(ctx.format_shortcut(&STOP_SHORTCUT)).clicked() || stop_pressed {
+                        self.scan_state.stop(); // Whether or not the thread finished meanwhile, the search ends here
+                        self.cancel.store(true, Ordering::Relaxed); // Signal the workers to stop, even mid-file
+                        self.pause.set(false); // Wake paused workers up so they see the cancellation
+                        self.paused_at = None;
+                        self.scan_results = None; // Stop listening for its results
+                        self.clear_results(); // Copies found so far are incomplete
+                    }
+                });
+            }
+
+            // A stopped search's thread may still be finishing its current files, nothing it finds is kept
+            // and a new search can start once it's done
+            if self.scan_state.get() == ScanState::Stopping {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("Stopping the search...");
+                });
+            }
+
+            // Button to clear the results and errors of the last scan
+            let has_results = !self.duplicates.is_empty() || !self.errors.is_empty();
+            ui.horizontal(|ui| {
+                if !self.scan_state.is_scanning() && has_results && ui.button("Clear Results").clicked() {
+                    self.clear_results();
+                }
+                // Undo the last batch sent to the Recycle Bin in this session
+                if let Some(batch) = self.trash_history.last() {
+                    let label = format!("Undo Recycle ({} items)", batch.paths.len());
+                    if ui.add_enabled(CAN_RESTORE && !self.scan_state.is_scanning(), egui::Button::new(label))
+                        .on_hover_text("Restore the files last sent to the Recycle Bin")
+                        .on_disabled_hover_text(if CAN_RESTORE { "Available once the scan is done" } else { "Restoring from the Recycle Bin isn't supported on this platform" })
+                        .clicked()
+                    {
+                        self.undo_trash();
+                    }
+                }
+                // Put the files last moved to a folder back where they were
+                if let Some(batch) = self.move_history.last() {
+                    let label = format!("Undo Move ({} items)", batch.moves.len());
+                    if ui.add_enabled(!self.scan_state.is_scanning(), egui::Button::new(label))
+                        .on_hover_text("Move the files last moved to a folder back where they were")
+                        .on_disabled_hover_text("Available once the scan is done")
+                        .clicked()
+                    {
+                        self.undo_move();
+                    }
+                }
+            });
+
+            // Progress display, a spinner while the files are being found and a bar while they're hashed
+            if self.scan_state.is_scanning() && !self.progress.discovered.load(Ordering::Relaxed) {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    let found = format_count(self.progress.files_found.load(Ordering::Relaxed));
+                    let paused = if self.pause.is_paused() { ", paused" } else { "" };
+                    ui.label(format!("Discovering files... {} found{}", found, paused));
+                });
+                let current_dir = self.progress.current_file.lock().unwrap().clone();
+                if !current_dir.is_empty() {
+                    ui.label(truncate_path(&current_dir, 80)).on_hover_text(&current_dir); // Show the full path on hover
+                }
+            } else if self.scan_state.is_scanning() {
+                // Weighted across the phases, so the bar keeps moving forward as the work shifts from one to the next
+                let fraction = self.progress.fraction();
+                ui.add(egui::ProgressBar::new(fraction).animate(true)
+                    .text(format!("{}: {:.0}%", self.progress.phase().label(), fraction * 100.0))
+                    .desired_height(24.0)); // Increase height of the progress bar
+                // Concrete counts and the estimated time left
+                let processed = self.progress.processed.load(Ordering::Relaxed);
+                let total = self.progress.total.load(Ordering::Relaxed);
+                let mut status = format!("{} of {} files", format_count(processed.min(total)), format_count(total));
+                let bytes_total = self.progress.bytes_total.load(Ordering::Relaxed);
+                if bytes_total > 0 {
+                    let bytes_done = self.progress.bytes_done.load(Ordering::Relaxed).min(bytes_total);
+                    status.push_str(&format!(" ({} of {})", format_size(bytes_done), format_size(bytes_total))); // What the bar follows
+                }
+                if self.pause.is_paused() {
+                    status.push_str(", paused");
+                } else if let Some(eta) = self.scan_started.and_then(|started| self.progress.eta(started.elapsed())) {
+                    status.push_str(&format!(", about {} left", format_duration(eta)));
+                }
+                ui.label(status);
+                let current_file = self.progress.current_file.lock().unwrap().clone();
+                if !current_file.is_empty() {
+                    ui.label(truncate_path(&current_file, 80)).on_hover_text(&current_file); // Show the full path on hover
+                }
+            } else {
+                // If not searching, disable progress bar animation
+                ui.add(egui::ProgressBar::new(0.0).desired_height(24.0));
+            }
+
+            // Confirmation dialog for deleting files
+            if let Some(pending) = self.pending_delete.clone() {
+                egui::Window::new("Confirm Deletion")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        let what = if pending.folders { "folders and everything in them" } else { "files" };
+                        if pending.permanent {
+                            ui.colored_label(egui::Color32::RED, format!("Permanently delete these {}? This cannot be undone.", what));
+                        } else {
+                            ui.label(format!("Send these {} to the Recycle Bin?", what));
+                        }
+                        egui::ScrollArea::vertical().id_salt("pending_delete").max_height(300.0).show(ui, |ui| {
+                            for path in &pending.paths {
+                                ui.monospace(path); // Show the full path of each file
+                            }
+                        });
+                        // Enter and Space would click a focused button, so the dialog only reacts to the mouse
+                        ui.input_mut(|input| {
+                            input.consume_key(egui::Modifiers::NONE, egui::Key::Enter);
+                            input.consume_key(egui::Modifiers::NONE, egui::Key::Space);
+                        });
+                        // Refuse to remove the last copy of a file; anything else gone for good is refused in safe mode, or only warned about
+                        let emptied = emptied_contents(&self.duplicates, &pending.paths);
+                        let paths: HashSet<String> = if pending.folders {
+                            // Every duplicate inside the folders goes with them
+                            let files = self.duplicates.values().flat_map(|group| &group.files);
+                            files.filter(|file| pending.paths.iter().any(|dir| Path::new(&file.path).starts_with(dir))).map(|file| file.path.clone()).collect()
+                        } else {
+                            pending.paths.iter().cloned().collect()
+                        };
+                        let selection = selection_summary(&self.duplicates, &paths);
+                        ui.strong(format!("{} files, {} in total", selection.count, format_size(selection.size)));
+                        let safe = selection.emptied_groups == 0 && (emptied.is_empty() || !self.safe_mode);
+                        if selection.emptied_groups > 0 {
+                            ui.colored_label(
+                                egui::Color32::RED,
+                                format!("{} group(s) would be left without any copy, keep at least one file of each", selection.emptied_groups),
+                            );
+                        }
+                        if !emptied.is_empty() {
+                            let color = if self.safe_mode { egui::Color32::RED } else { egui::Color32::YELLOW };
+                            let advice = if self.safe_mode { "keep at least one file of each" } else { "safe mode is off, they'd be gone for good" };
+                            ui.colored_label(color, format!("{} content(s) would be left without any copy, {}:", emptied.len(), advice));
+                            egui::ScrollArea::vertical().id_salt("emptied_contents").max_height(120.0).show(ui, |ui| {
+                                ui.monospace(describe_emptied(&emptied, self.hash_digits)); // Exactly which groups would be emptied
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            if pending.permanent {
+                                // Second confirmation step for permanent deletion
+                                if ui.add_enabled(safe, egui::Button::new("Confirm: Delete Permanently")).clicked() {
+                                    self.delete_files(&pending.paths, true);
+                                    self.pending_delete = None; // Close the dialog
+                                }
+                                if ui.button("Back").clicked() {
+                                    self.pending_delete = Some(PendingDelete { permanent: false, ..pending.clone() }); // Return to the default action
+                                }
+                            } else {
+                                if ui.add_enabled(safe, egui::Button::new("Confirm: Send to Recycle Bin")).clicked() {
+                                    self.delete_files(&pending.paths, false);
+                                    self.pending_delete = None; // Close the dialog
+                                }
+                                if ui.add_enabled(safe, egui::Button::new("Delete Permanently...")).clicked() {
+                                    self.pending_delete = Some(PendingDelete { permanent: true, ..pending.clone() }); // Ask once more
+                                }
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.pending_delete = None; // Close the dialog
+                            }
+                        });
+                    });
+            }
+
+            // Window for pasting or loading a list of files to scan instead of the directories
+            if self.file_list_open {
+                let mut open = true;
+                let mut start = false;
+                egui::Window::new("Scan File List").open(&mut open).collapsible(false).default_width(500.0).show(ctx, |ui| {
+                    ui.label("Paste one path per line, or load a text file of them. Exactly these files are compared, with the file filters applied.");
+                    egui::ScrollArea::vertical().id_salt("file_list").max_height(300.0).show(ui, |ui| {
+                        ui.add(egui::TextEdit::multiline(&mut self.file_list_text).desired_rows(10).desired_width(f32::INFINITY).hint_text("/path/to/file"));
+                    });
+                    let count = parse_file_list(&self.file_list_text).len();
+                    ui.horizontal(|ui| {
+                        if ui.button("Load from File...").clicked() {
+                            if let Some(path) = FileDialog::new().add_filter("Text", &["txt", "lst"]).pick_file() {
+                                match fs::read_to_string(extended_path(&path)) {
+                                    Ok(text) => self.file_list_text = text,
+                                    Err(e) => self.error_message = Some(format!("Failed to read {}: {}", path.display(), e)),
+                                }
+                            }
+                        }
+                        start = ui.add_enabled(count > 0 && !self.scan_state.is_busy(), egui::Button::new(format!("Scan {} files", format_count(count)))).clicked();
+                    });
+                });
+                if start {
+                    self.file_list = Some(parse_file_list(&self.file_list_text));
+                    self.start_search(ctx); // No directory to warn about
+                    open = false;
+                }
+                self.file_list_open = open;
+            }
+
+            // Confirmation dialog for scanning drive roots or system directories, which reads far more than intended
+            if !self.pending_system_scan.is_empty() {
+                egui::Window::new("Scan System Directories?")
+                    .collapsible(false)
+                    .resizable(false)
+                    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                    .show(ctx, |ui| {
+                        ui.colored_label(egui::Color32::YELLOW, "This scan would go through files that are almost never meant to be scanned:");
+                        for (dir, reason) in &self.pending_system_scan {
+                            ui.horizontal(|ui| {
+                                ui.monospace(dir);
+                                ui.label(format!("is {}", reason));
+                            });
+                        }
+                        ui.label("Reading them can take hours and keeps the disk busy the whole time.");
+                        ui.horizontal(|ui| {
+                            if ui.button("Scan Anyway").clicked() {
+                                self.pending_system_scan.clear();
+                                self.start_search(ctx);
+                            }
+                            if ui.button("Cancel").clicked() {
+                                self.pending_system_scan.clear(); // Back to picking directories
+                            }
+                        });
+                    });
+            }
+
+            // Line-by-line comparison of two text files
+            if let Some(diff) = &self.text_diff {
+                let mut open = true;
+                egui::Window::new("Text Comparison").open(&mut open).default_size([700.0, 500.0]).show(ctx, |ui| {
+                    ui.label(egui::RichText::new(format!("--- {}", diff.left)).color(egui::Color32::RED)).on_hover_text(&diff.left);
+                    ui.label(egui::RichText::new(format!("+++ {}", diff.right)).color(egui::Color32::GREEN)).on_hover_text(&diff.right);
+                    ui.separator();
+                    if diff.hunks.is_empty() {
+                        ui.strong("The files are identical.");
+                    }
+                    egui::ScrollArea::both().id_salt("text_diff").auto_shrink(false).show(ui, |ui| {
+                        for (i, hunk) in diff.hunks.iter().enumerate() {
+                            if i > 0 {
+                                ui.separator(); // Unchanged lines left out between the hunks
+                            }
+                            for (tag, line) in hunk {
+                                let (sign, color) = match tag {
+                                    ChangeTag::Delete => ("-", Some(egui::Color32::RED)),
+                                    ChangeTag::Insert => ("+", Some(egui::Color32::GREEN)),
+                                    ChangeTag::Equal => (" ", None),
+                                };
+                                let text = egui::RichText::new(format!("{} {}", sign, line.trim_end_matches(['\r', '\n']))).monospace();
+                                ui.label(if let Some(color) = color { text.color(color) } else { text });
+                            }
+                        }
+                    });
+                });
+                if !open {
+                    self.text_diff = None; // Closed by the user
+                }
+            }
+
+            // Two exports side by side, to follow the duplicates over time; nothing can be deleted from here
+            if let Some(view) = &mut self.snapshots {
+                let digits = self.hash_digits;
+                let mut open = true;
+                let mut load = None; // Side to load an export into, asked once the window is drawn
+                egui::Window::new("Compare Snapshots").open(&mut open).default_size([900.0, 500.0]).show(ctx, |ui| {
+                    match &view.diff {
+                        Some(diff) => {
+                            ui.label(diff.summary());
+                            if diff.disjoint() {
+                                ui.colored_label(
+                                    egui::Color32::YELLOW,
+                                    "No group shares a hash between the exports; they may have been made with another hash algorithm or mode",
+                                );
+                            }
+                        }
+                        None => {
+                            ui.label("Load an older and a newer export, in CSV or JSON, to see how the duplicates changed in between.");
+                        }
+                    }
+                    if let Some(e) = &view.error {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                    ui.separator();
+                    ui.columns(2, |columns| {
+                        for (side, ui) in columns.iter_mut().enumerate() {
+                            let (title, snapshot) = if side == 0 { ("Older", &view.older) } else { ("Newer", &view.newer) };
+                            ui.horizontal(|ui| {
+                                ui.strong(title);
+                                if ui.button("Load...").clicked() {
+                                    load = Some(side);
+                                }
+                            });
+                            let Some(snapshot) = snapshot else {
+                                ui.weak("No export loaded");
+                                continue;
+                            };
+                            let files: usize = snapshot.groups.iter().map(|group| group.files.len()).sum();
+                            ui.label(format!("{} ({} groups, {} files)", truncate_path(&snapshot.source, 50), format_count(snapshot.groups.len()), format_count(files)))
+                                .on_hover_text(&snapshot.source);
+                            let (Some(diff), Some(older), Some(newer)) = (&view.diff, &view.older, &view.newer) else { continue };
+                            // What left the older export on the left, what came in the newer one on the right
+                            if side == 0 {
+                                let resolved: Vec<_> = diff.resolved.iter().map(|&i| (&older.groups[i], None)).collect();
+                                show_snapshot_groups(ui, "snapshot_resolved", "Resolved", egui::Color32::GREEN, &resolved, digits);
+                                let changed: Vec<_> = diff.changed.iter().map(|&(before, after)| (&older.groups[before], Some(&newer.groups[after]))).collect();
+                                show_snapshot_groups(ui, "snapshot_changed_before", "Changed, before", egui::Color32::YELLOW, &changed, digits);
+                            } else {
+                                let added: Vec<_> = diff.added.iter().map(|&i| (&newer.groups[i], None)).collect();
+                                show_snapshot_groups(ui, "snapshot_added", "Added", egui::Color32::RED, &added, digits);
+                                let changed: Vec<_> = diff.changed.iter().map(|&(before, after)| (&newer.groups[after], Some(&older.groups[before]))).collect();
+                                show_snapshot_groups(ui, "snapshot_changed_after", "Changed, after", egui::Color32::YELLOW, &changed, digits);
+                            }
+                        }
+                    });
+                });
+                if let Some(side) = load {
+                    if let Some(path) = FileDialog::new().add_filter("CSV or JSON", &["csv", "json"]).pick_file() {
+                        match read_export(&path) {
+                            Ok(groups) => {
+                                let snapshot = Some(Baseline { source: path.display().to_string(), groups });
+                                if side == 0 { view.older = snapshot } else { view.newer = snapshot }
+                                view.error = None;
+                            }
+                            Err(e) => view.error = Some(format!("Failed to import {}: {}", path.display(), e)),
+                        }
+                        // Worked out once per load, exports of large scans hold many groups
+                        view.diff = match (&view.older, &view.newer) {
+                            (Some(older), Some(newer)) => Some(compare_snapshots(&older.groups, &newer.groups)),
+                            _ => None,
+                        };
+                    }
+                }
+                if !open {
+                    self.snapshots = None; // Closed by the user
+                }
+            }
+
+            // Display found duplicates
+            let duplicates_map = &self.duplicates; // Found duplicates
+            let verified = &self.verified; // Verified groups
+
+            let locked = self.scan_state.is_scanning(); // Results of a running search are incomplete, so nothing can be done with them yet
+
+            // Export controls, disabled while there is nothing to export
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!duplicates_map.is_empty() && !locked, |ui| {
+                    egui::ComboBox::from_id_salt("export_format")
+                        .selected_text(self.export_format.label())
+                        .show_ui(ui, |ui| {
+                            for format in ExportFormat::ALL {
+                                ui.selectable_value(&mut self.export_format, format, format.label());
+                            }
+                        });
+                    if self.export_format == ExportFormat::Html {
+                        ui.checkbox(&mut self.export_thumbnails, "Thumbnails").on_hover_text("Embed previews of the images, making the file bigger");
+                    }
+
+                    if ui.button("Export Results").on_hover_text(ctx.format_shortcut(&EXPORT_SHORTCUT)).clicked() || export_pressed {
+                        let extension = self.export_format.extension();
+                        if let Some(path) = FileDialog::new()
+                            .add_filter(self.export_format.label(), &[extension])
+                            .set_file_name(format!("duplicates.{}", extension))
+                            .save_file() // Ask where to save the results
+                        {
+                            let groups = export_groups(duplicates_map);
+                            self.error_message = write_export(&path, self.export_format, &groups, self.export_thumbnails)
+                                .err()
+                                .map(|e| format!("Failed to export results: {}", e)); // Report any write error
+                        }
+                    }
+                });
+
+                // Earlier export to compare with, like last week's results in a recurring audit
+                if ui.button("Compare with Export...").on_hover_text("Load an earlier CSV or JSON export and list what changed since").clicked() {
+                    if let Some(path) = FileDialog::new().add_filter("CSV or JSON", &["csv", "json"]).pick_file() {
+                        match read_export(&path) {
+                            Ok(groups) => {
+                                self.baseline = Some(Baseline { source: path.display().to_string(), groups });
+                                self.error_message = None;
+                            }
+                            Err(e) => self.error_message = Some(format!("Failed to import {}: {}", path.display(), e)),
+                        }
+                    }
+                }
+                if ui.button("Compare Snapshots...").on_hover_text("Load two earlier exports side by side and list the groups added and resolved in between").clicked() {
+                    self.snapshots = Some(SnapshotView::default());
+                }
+            });
+
+            // Display the last error, if any
+            if let Some(message) = &self.error_message {
+                ui.colored_label(egui::Color32::RED, message);
+            }
+
+            // Differences between the current results and an imported export, worked out again every frame to follow deletions
+            let mut close_baseline = false;
+            if let Some(baseline) = &self.baseline {
+                let comparison = compare_results(duplicates_map, &baseline.groups);
+                let header = format!("Compared with {} ({})", truncate_path(&baseline.source, 60), comparison.summary());
+                egui::CollapsingHeader::new(header).id_salt("baseline").show(ui, |ui| {
+                    if locked {
+                        ui.weak("The scan is still running, so groups may be missing from the current results");
+                    }
+                    if comparison.disjoint() {
+                        ui.colored_label(
+                            egui::Color32::YELLOW,
+                            "No group shares a hash with the export; it may have been made with another hash algorithm or mode",
+                        );
+                    }
+                    let lists = [
+                        ("New", comparison.new.iter().map(|group| (group.hash.as_str(), group.files.iter().map(|file| file.path.as_str()).collect())).collect::<Vec<(&str, Vec<&str>)>>()),
+                        ("Changed", comparison.changed.iter().map(|group| (group.hash.as_str(), group.files.iter().map(|file| file.path.as_str()).collect())).collect()),
+                        ("Resolved", comparison.resolved.iter().map(|group| (group.hash.as_str(), group.files.iter().map(String::as_str).collect())).collect()),
+                    ];
+                    for (kind, groups) in lists {
+                        ui.collapsing(format!("{} groups ({})", kind, groups.len()), |ui| {
+                            egui::ScrollArea::vertical().id_salt(kind).max_height(200.0).show(ui, |ui| {
+                                for (hash, files) in groups {
+                                    ui.strong(format!("{} ({} files)", short_hash(hash, self.hash_digits), files.len())).on_hover_text(hash);
+                                    for file in files {
+                                        ui.monospace(file);
+                                    }
+                                }
+                            });
+                        });
+                    }
+                    close_baseline = ui.button("Stop Comparing").clicked();
+                });
+            }
+            if close_baseline {
+                self.baseline = None;
+            }
+
+            // Display the files that couldn't be read during the scan
+            let errors = &self.errors; // Files that couldn't be read
+            if !errors.is_empty() {
+                let header = egui::RichText::new(format!("Errors ({})", errors.len())).color(egui::Color32::RED);
+                ui.collapsing(header, |ui| {
+                    egui::ScrollArea::vertical().id_salt("errors").max_height(150.0).show(ui, |ui| {
+                        for (path, e) in errors.iter() {
+                            ui.label(format!("{}: {}", path.display(), e)); // Show the path and the reason
+                        }
+                    });
+                });
+            }
+
+            // Statistics of the last search
+            if let Some(summary) = &self.scan_summary {
+                ui.label(summary);
+            }
+
+            let mut hardlink_group: Option<Vec<String>> = None; // Group whose copies should be replaced with hard links
+            let mut to_ignore: Option<Vec<String>> = None; // Files to add to the ignore list
+            let mut to_move: Option<(Vec<String>, PathBuf)> = None; // Files to move, and the folder to move them to
+            let mut to_compare: Option<(String, String)> = None; // Text files to compare line by line
+            let mut to_copy: Option<(String, String)> = None; // Text to put on the clipboard, with the confirmation to show
+            if let Some(details) = self.nothing_found.as_ref().filter(|_| duplicates_map.is_empty()) {
+                ui.heading("No duplicates found");
+                ui.label(details);
+            }
+            if !duplicates_map.is_empty() {
+                ui.heading(&self.results_title); // Heading for duplicates section
+                if duplicates_map.values().any(|group| group.approximate) {
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        "Approximate results: these files only have similar sizes and may differ completely. \
+                         Compare them yourself before deleting anything.",
+                    );
+                }
+                if locked {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.colored_label(egui::Color32::YELLOW, "Scan in progress: groups may still change, actions are available once it's done");
+                    });
+                }
+
+                // Search box narrowing the results down to groups with a matching path
+                ui.horizontal(|ui| {
+                    ui.label("Search results:");
+                    ui.add(egui::TextEdit::singleline(&mut self.results_query).hint_text("part of a path"));
+                    ui.checkbox(&mut self.results_query_regex, "Regex");
+                    ui.label("Min. copies:");
+                    ui.add(egui::DragValue::new(&mut self.min_copies).range(2..=1000))
+                        .on_hover_text("Only show groups with at least this many files, the most redundant content");
+                });
+                let query = parse_results_query(&self.results_query, self.results_query_regex);
+                if let Err(e) = &query {
+                    ui.colored_label(egui::Color32::RED, format!("Invalid pattern: {}", e)); // Everything stays shown meanwhile
+                }
+                let query = query.ok().flatten();
+                let shown: Vec<_> = sorted_groups(duplicates_map, self.sort_order)
+                    .into_iter()
+                    .filter(|(_, group)| group.files.len() >= self.min_copies)
+                    .filter(|(_, group)| query.as_ref().is_none_or(|query| group.files.iter().any(|file| query.is_match(&file.path))))
+                    .collect();
+
+                // Space freed by keeping one copy of each; files of similar size may all be worth keeping
+                let reclaimable: u64 = shown.iter().filter(|(_, group)| !group.approximate).map(|(_, group)| group.wasted_space()).sum();
+                if shown.len() < duplicates_map.len() {
+                    ui.label(format!("Reclaimable space: {} in {} of {} groups", format_size(reclaimable), shown.len(), duplicates_map.len()));
+                } else {
+                    ui.label(format!("Reclaimable space: {}", format_size(reclaimable)));
+                }
+
+                // Where the redundancy lives, by kind of file
+                if !locked {
+                    egui::CollapsingHeader::new("Statistics by extension").show(ui, |ui| {
+                        egui::ScrollArea::vertical().id_salt("extension_stats").max_height(200.0).show(ui, |ui| {
+                            egui::Grid::new("extension_stats_grid").striped(true).num_columns(4).show(ui, |ui| {
+                                for heading in ["Extension", "Groups", "Files", "Wasted"] {
+                                    ui.strong(heading);
+                                }
+                                ui.end_row();
+                                for stats in extension_stats(shown.iter().map(|(_, group)| *group)) {
+                                    ui.label(&stats.extension);
+                                    ui.label(format_count(stats.groups));
+                                    ui.label(format_count(stats.files));
+                                    ui.label(format_size(stats.wasted));
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    });
+                }
+
+                // Folders that are identical copies, which can be removed as a whole
+                if !self.duplicate_dirs.is_empty() {
+                    egui::CollapsingHeader::new(format!("Duplicate folders ({})", self.duplicate_dirs.len())).default_open(true).show(ui, |ui| {
+                        egui::ScrollArea::vertical().id_salt("duplicate_dirs").max_height(200.0).show(ui, |ui| {
+                            for group in &self.duplicate_dirs {
+                                ui.strong(format!("{} folders with {} files ({} each)", group.dirs.len(), group.files, format_size(group.size)));
+                                for dir in &group.dirs {
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("🗑").on_hover_text("Send folder to Recycle Bin").clicked() {
+                                            self.pending_delete = Some(PendingDelete { paths: vec![dir.clone()], permanent: false, folders: true });
+                                        }
+                                        if ui.small_button("📂").on_hover_text("Open in folder").clicked() {
+                                            self.error_message = reveal_in_file_manager(dir).err(); // Report a missing folder
+                                        }
+                                        ui.label(dir);
+                                    });
+                                }
+                            }
+                        });
+                    });
+                }
+
+                // Switch between the views, and choose the order of the groups
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.results_view, ResultsView::ByHash, "Group by content");
+                    ui.selectable_value(&mut self.results_view, ResultsView::ByFolder, "Group by folder");
+                    if self.results_view == ResultsView::ByHash {
+                        egui::ComboBox::from_label("Sort by")
+                            .selected_text(self.sort_order.label())
+                            .show_ui(ui, |ui| {
+                                for order in SortOrder::ALL {
+                                    ui.selectable_value(&mut self.sort_order, order, order.label());
+                                }
+                            });
+                        ui.label("Hash digits:");
+                        ui.add(egui::DragValue::new(&mut self.hash_digits).range(4..=64))
+                            .on_hover_text("Characters of each hash shown in the group headers; hover a header for the full hash");
+                    }
+                });
+                if self.compared {
+                    ui.checkbox(&mut self.hide_first_folder, "Only show copies outside the first folder");
+                }
+
+                // Automatic selection, keeping a single copy of each group by modification time or path length
+                ui.add_enabled_ui(!locked, |ui| ui.horizontal(|ui| {
+                    if ui.button("Select all but newest").clicked() {
+                        self.selected = select_all_but(duplicates_map, true);
+                        self.kept_by_rule.clear();
+                        self.kept_paths.clear();
+                    }
+                    if ui.button("Select all but oldest").clicked() {
+                        self.selected = select_all_but(duplicates_map, false);
+                        self.kept_by_rule.clear();
+                        self.kept_paths.clear();
+                    }
+                    if ui.button("Keep shortest path").on_hover_text("Select every file but the one with the shortest path, often the canonical location").clicked() {
+                        (self.selected, self.kept_paths) = select_by_path_length(duplicates_map, true);
+                        self.kept_by_rule.clear();
+                    }
+                    if ui.button("Keep longest path").on_hover_text("Select every file but the one with the longest path").clicked() {
+                        (self.selected, self.kept_paths) = select_by_path_length(duplicates_map, false);
+                        self.kept_by_rule.clear();
+                    }
+                    if self.result_roots.len() > 1
+                        && ui.button("Select by directory order")
+                            .on_hover_text("Keep the copies in the highest directory of the list holding one, select the others")
+                            .clicked()
+                    {
+                        // Ranked by the current order of the list, which may have changed since the scan
+                        let ranks: Vec<usize> = self.result_roots
+                            .iter()
+                            .map(|dir| self.dirs_to_scan.iter().position(|listed| listed == dir).unwrap_or(usize::MAX))
+                            .collect();
+                        self.selected = select_by_root_order(duplicates_map, &ranks);
+                        self.kept_by_rule.clear();
+                        self.kept_paths.clear();
+                    }
+                    if let Some(root) = self.primary_root {
+                        if ui.button("Select all but primary").on_hover_text("Keep the copies in the primary directory").clicked() {
+                            self.selected = select_all_but_root(duplicates_map, root);
+                            self.kept_by_rule.clear();
+                            self.kept_paths.clear();
+                        }
+                    }
+                    if ui.add_enabled(!self.keep_rules.is_empty(), egui::Button::new("Select by keep rules"))
+                        .on_hover_text("Keep the file matching the first rule in each group")
+                        .on_disabled_hover_text("Add keep rules in the settings first")
+                        .clicked()
+                    {
+                        (self.selected, self.kept_by_rule) = select_by_rules(duplicates_map, &self.keep_rules, self.case_sensitive_names);
+                        self.kept_paths.clear();
+                    }
+                    if !self.selected.is_empty() && ui.button("Clear selection").clicked() {
+                        self.selected.clear();
+                        self.kept_by_rule.clear();
+                        self.kept_paths.clear();
+                    }
+                }));
+
+                // Batch deletion of the selected files
+                let selection = selection_summary(duplicates_map, &self.selected);
+                ui.horizontal(|ui| {
+                    ui.label(format!("Selected: {} files ({})", selection.count, format_size(selection.size)));
+                    if ui.add_enabled(selection.count > 0 && !locked, egui::Button::new("Delete Selected")).clicked() {
+                        let mut paths: Vec<String> = self.selected.iter().cloned().collect();
+                        paths.sort(); // List them in a predictable order
+                        self.pending_delete = Some(PendingDelete { paths, permanent: false, folders: false }); // Ask for confirmation first
+                    }
+                    // Safer than deleting, the files can be reviewed in one place and put back
+                    if ui.add_enabled(selection.count > 0 && !locked, egui::Button::new("Move Selected..."))
+                        .on_hover_text("Move the selected files into a folder, like a quarantine to review before deleting them")
+                        .clicked()
+                    {
+                        let dialog = FileDialog::new().set_title("Move the selected files to");
+                        let dialog = if self.move_destination.is_empty() { dialog } else { dialog.set_directory(&self.move_destination) };
+                        if let Some(dest) = dialog.pick_folder() {
+                            let mut paths: Vec<String> = self.selected.iter().cloned().collect();
+                            paths.sort(); // Name collisions are numbered in a predictable order
+                            self.move_destination = dest.display().to_string();
+                            to_move = Some((paths, dest)); // Moved once the results are no longer borrowed
+                        }
+                    }
+                    // The same files as the deletion, for reviewing and running by hand
+                    if ui.add_enabled(selection.count > 0 && !locked, egui::Button::new("Export Deletion Plan..."))
+                        .on_hover_text("Write the selected files as delete commands or a plain list, without deleting anything")
+                        .clicked()
+                    {
+                        let mut paths: Vec<String> = self.selected.iter().cloned().collect();
+                        paths.sort(); // In the order the deletion would list them
+                        let (script, extension) = if cfg!(windows) { ("Batch file", "bat") } else { ("Shell script", "sh") };
+                        let emptied = emptied_contents(duplicates_map, &paths);
+                        if selection.emptied_groups > 0 || (self.safe_mode && !emptied.is_empty()) {
+                            self.error_message = Some("The plan would leave some content without any copy, keep at least one file of each group".to_string());
+                        } else if let Some(path) = FileDialog::new()
+                            .add_filter(script, &[extension])
+                            .add_filter("Plain list", &["txt"])
+                            .set_file_name(format!("deletion_plan.{}", extension))
+                            .save_file()
+                        {
+                            self.error_message = write_deletion_plan(&path, &paths).err().map(|e| format!("Failed to export the deletion plan: {}", e));
+                        }
+                    }
+                    ui.checkbox(&mut self.safe_mode, "Safe mode")
+                        .on_hover_text("Refuse any deletion that would remove every copy of some content, across all groups");
+                });
+                if selection.emptied_groups > 0 {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("Warning: every file is selected in {} group(s), no copy would be kept", selection.emptied_groups),
+                    );
+                }
+
+                if self.results_view == ResultsView::ByFolder {
+                    egui::ScrollArea::vertical().id_salt("folder_tree").show(ui, |ui| {
+                        let tree = FolderNode::build(shown.iter().map(|(_, group)| *group));
+                        show_folder_tree(ui, &tree, Path::new(""), locked, &mut self.selected, &mut self.error_message, &mut to_ignore);
+                    });
+                } else {
+                    // Only the groups in view are built, the others just take up the height they had when last shown,
+                    // so huge results stay responsive; groups vary in height once expanded, which rules out `show_rows`
+                    egui::ScrollArea::vertical().show_viewport(ui, |ui, viewport| {
+                        let origin = ui.cursor().top(); // Where the content starts on screen
+                        let estimate = ui.spacing().interact_size.y + ui.spacing().item_spacing.y; // A collapsed header never shown yet
+                        let mut skipped = 0.0; // Height of the groups left out since the last one built
+                        for &(hash, group) in &shown { // Iterate over found duplicates in the chosen order
+                            let top = ui.cursor().top() - origin + skipped;
+                            let height = self.group_heights.get(hash).copied().unwrap_or(estimate);
+                            if top > viewport.max.y || top + height < viewport.min.y {
+                                skipped += height; // Out of view, not even its header is built
+                                continue;
+                            }
+                            ui.add_space(std::mem::take(&mut skipped));
+                            let start = ui.cursor().top();
+                            let files = &group.files;
+                            let summary = format!("{} files, {} wasted", files.len(), format_size(group.wasted_space()));
+                            // Mark verified groups in green so they stand out from hash-only matches
+                            let header = if group.is_empty_files() {
+                                egui::RichText::new(format!("Empty files ({} files)", files.len())).weak() // Set apart from real duplicates
+                            } else if group.approximate {
+                                let largest = files.iter().map(|file| file.size).max().unwrap_or(group.size);
+                                let sizes = format!("{} to {}", format_size(group.size), format_size(largest));
+                                egui::RichText::new(format!("Similar sizes ({} files, {}, approximate)", files.len(), sizes)).color(egui::Color32::YELLOW)
+                            } else if let Some(distance) = group.distance {
+                                egui::RichText::new(format!("Similar images ({}, distance ≤ {})", summary, distance)) // Perceptual match
+                            } else {
+                                // Normalized text files match without being identical, which the header says
+                                let kind = if group.normalized { ", text normalized" } else { "" };
+                                let hash = short_hash(hash, self.hash_digits);
+                                if verified.contains(&group.hash) {
+                                    egui::RichText::new(format!("Hash: {} ({}{}, verified)", hash, summary, kind)).color(egui::Color32::GREEN)
+                                } else {
+                                    egui::RichText::new(format!("Hash: {} ({}{}, unverified)", hash, summary, kind))
+                                }
+                            };
+                            let response = egui::CollapsingHeader::new(header).id_salt(hash).show(ui, |ui| {
+                                // Recycle every copy except the first one
+                                if group.approximate {
+                                    ui.colored_label(egui::Color32::RED, "Not verified duplicates: only their sizes are close. Check each file before deleting it.");
+                                }
+                                ui.add_enabled_ui(!locked, |ui| ui.horizontal(|ui| {
+                                    // Files of similar size are never deleted in bulk, only one by one after a closer look
+                                    if !group.approximate && ui.button("Send duplicates to Recycle Bin (keep first)").clicked() {
+                                        let loose = files[1..].iter().filter(|file| split_archive_path(&file.path).is_none()); // Archives are left alone
+                                        let paths = loose.map(|file| file.path.clone()).collect();
+                                        self.pending_delete = Some(PendingDelete { paths, permanent: false, folders: false }); // Ask for confirmation first
+                                    }
+                                    // Keep every path but store the data once; similar files aren't identical, so they can't share data
+                                    let in_archive = files.iter().any(|file| split_archive_path(&file.path).is_some());
+                                    if group.distance.is_none()
+                                        && !group.approximate
+                                        && !group.normalized
+                                        && !in_archive
+                                        && ui.button("Replace with hardlinks (keep first)")
+                                            .on_hover_text("Replace the other copies with hard links to the first file")
+                                            .clicked()
+                                    {
+                                        let paths = files.iter().map(|file| file.path.clone()).collect();
+                                        hardlink_group = Some(paths); // Replaced once the results are no longer borrowed
+                                    }
+                                    if ui.button("Ignore group").on_hover_text("Never report these files again").clicked() {
+                                        to_ignore = Some(files.iter().map(|file| file.path.clone()).collect());
+                                    }
+                                    if ui.button("📋 Copy hash").on_hover_text("Copy the full hash of the group to the clipboard").clicked() {
+                                        to_copy = Some((hash.clone(), "Copied the hash".to_string()));
+                                    }
+                                    if ui.button("📋 Copy paths").on_hover_text("Copy the paths of every file in the group, one per line").clicked() {
+                                        let paths: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+                                        to_copy = Some((paths.join("\n"), format!("Copied {} paths", paths.len())));
+                                    }
+                                    // Compare the two selected files of the group, or its first two; images are never text
+                                    if group.distance.is_none() && ui.button("Compare text").on_hover_text("Show the differences between two text files of the group, checked ones first").clicked() {
+                                        let checked: Vec<&FileEntry> = files.iter().filter(|file| self.selected.contains(&file.path)).collect();
+                                        let pair = if checked.len() == 2 { [checked[0], checked[1]] } else { [&files[0], &files[1]] };
+                                        to_compare = Some((pair[0].path.clone(), pair[1].path.clone()));
+                                    }
+                                }));
+                                if let Some(rule) = self.kept_by_rule.get(hash).and_then(|&i| self.keep_rules.get(i).map(|rule| (i, rule))) {
+                                    ui.weak(format!("Kept by rule {}: {}", rule.0 + 1, rule.1)); // Why the selection looks the way it does
+                                }
+                                for file in files { // List each file under the corresponding hash
+                                    if self.compared && self.hide_first_folder && file.root == 0 {
+                                        continue; // Only the redundant copies are of interest
+                                    }
+                                    ui.horizontal(|ui| {
+                                        // Checkbox marking the file for deletion, except for files inside archives
+                                        let deletable = split_archive_path(&file.path).is_none() && !locked;
+                                        let mut selected = self.selected.contains(&file.path);
+                                        if ui.add_enabled(deletable, egui::Checkbox::without_text(&mut selected))
+                                            .on_disabled_hover_text(if locked { "Available once the scan is done" } else { "Files inside archives can't be deleted" })
+                                            .changed()
+                                        {
+                                            if selected {
+                                                self.selected.insert(file.path.clone());
+                                            } else {
+                                                self.selected.remove(&file.path);
+                                            }
+                                        }
+                                        if ui.add_enabled(deletable, egui::Button::new("🗑").small()).on_hover_text("Send file to Recycle Bin").clicked() {
+                                            self.pending_delete = Some(PendingDelete { paths: vec![file.path.clone()], permanent: false, folders: false }); // Ask for confirmation first
+                                        }
+                                        if ui.small_button("📂").on_hover_text("Open in folder").clicked() {
+                                            self.error_message = reveal_in_file_manager(&file.path).err(); // Report a missing file
+                                        }
+                                        if ui.small_button("📋").on_hover_text("Copy path").clicked() {
+                                            to_copy = Some((file.path.clone(), "Copied the path".to_string()));
+                                        }
+                                        if ui.add_enabled(!locked, egui::Button::new("🚫").small()).on_hover_text("Ignore this file in future scans").clicked() {
+                                            to_ignore = Some(vec![file.path.clone()]);
+                                        }
+                                        // Groups are only built when expanded, so thumbnails load lazily
+                                        if is_image(Path::new(&file.path)) {
+                                            if let Some(texture) = thumbnail(ctx, &mut self.thumbnails, &file.path) {
+                                                ui.add(egui::Image::new(&texture).max_size(egui::vec2(THUMBNAIL_SIZE, THUMBNAIL_SIZE)));
+                                            }
+                                        }
+                                        // Where the copy lives, like a local folder or a network share, once there's a choice
+                                        if let Some(dir) = self.result_roots.get(file.root).filter(|_| self.result_roots.len() > 1) {
+                                            source_chip(ui, file.root, dir);
+                                        }
+                                        file_icon(ui, &file.path);
+                                        if self.kept_paths.get(hash) == Some(&file.path) {
+                                            ui.colored_label(egui::Color32::GREEN, "✔ Keep").on_hover_text("Kept by the selection by path length");
+                                        }
+                                        if self.primary_root == Some(file.root) {
+                                            ui.label(egui::RichText::new(format!("⭐ {}", file.path)).strong()).on_hover_text("Copy in the primary directory");
+                                        } else {
+                                            ui.label(&file.path); // Display file path
+                                        }
+                                    });
+                                }
+                            });
+                            response.header_response.on_hover_text(hash); // Full hash, the header only shows its start
+                            self.group_heights.insert(hash.clone(), ui.cursor().top() - start); // Space to keep once it's out of view
+                        }
+                        ui.add_space(skipped); // The groups below the view, so the scroll bar stays true
+                    });
+                }
+            }
+            if let Some(paths) = hardlink_group {
+                self.hardlink_files(&paths);
+            }
+            if let Some(paths) = to_ignore {
+                self.ignore_files(&paths);
+            }
+            if let Some((paths, dest)) = to_move {
+                self.move_files(&paths, &dest);
+            }
+            if let Some((left, right)) = to_compare {
+                match text_diff(&left, &right) {
+                    Ok(diff) => self.text_diff = Some(diff),
+                    Err(e) => self.error_message = Some(e), // Binary or unreadable files
+                }
+            }
+            if let Some((text, message)) = to_copy {
+                self.copy_to_clipboard(ctx, text, message);
+            }
+        });
+
+        // Toast confirming the last copy, gone after a moment
+        let remaining = self.toast.as_ref().map(|(_, shown_at)| TOAST_DURATION.saturating_sub(shown_at.elapsed()));
+        match (&self.toast, remaining) {
+            (Some(_), Some(remaining)) if remaining.is_zero() => self.toast = None,
+            (Some((message, _)), Some(remaining)) => {
+                egui::Area::new(egui::Id::new("toast"))
+                    .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -40.0])
+                    .order(egui::Order::Foreground)
+                    .interactable(false)
+                    .show(ctx, |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| ui.label(message));
+                    });
+                ctx.request_repaint_after(remaining); // Hide it in time even without input
+            }
+            _ => {}
+        }
+    }
+}
+
+// Command-line options for running a scan without the GUI
+#[derive(Parser)]
+#[command(name = "DupFinder", about = "Find duplicate files. Launches the GUI when run without arguments.")]
+struct Cli {
+    /// Directory to scan (can be given several times)
+    #[arg(long = "scan", value_name = "DIR", required_unless_present = "files")]
+    scan: Vec<String>,
+
+    /// Scan exactly the files listed in this text file, one path per line like the output of find or fd; - for stdin
+    #[arg(long, value_name = "FILE", conflicts_with = "scan")]
+    files: Option<PathBuf>,
+
+    /// File to write the results to (.csv for CSV, .html for a report page, anything else for JSON); prints JSON to stdout if omitted
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Embed thumbnails of the images in an HTML report
+    #[arg(long)]
+    thumbnails: bool,
+
+    /// Hashing algorithm
+    #[arg(long, value_enum, default_value_t = HashAlgo::Sha256)]
+    algo: HashAlgo,
+
+    /// Comma-separated extensions to scan, all if omitted
+    #[arg(long, value_name = "EXTS")]
+    include: Option<String>,
+
+    /// Directory to skip, with everything inside it (can be given several times)
+    #[arg(long, value_name = "DIR")]
+    exclude_dir: Vec<PathBuf>,
+
+    /// Comma-separated extensions to skip
+    #[arg(long, value_name = "EXTS")]
+    exclude: Option<String>,
+
+    /// Only scan files whose name matches this glob, like "*.bak"
+    #[arg(long, value_name = "GLOB", conflicts_with = "name_regex")]
+    name: Option<String>,
+
+    /// Only scan files whose name matches this regular expression, like "IMG_\d+"
+    #[arg(long, value_name = "REGEX")]
+    name_regex: Option<String>,
+
+    /// Minimum file size in bytes (inclusive)
+    #[arg(long, value_name = "BYTES", default_value_t = 0)]
+    min_size: u64,
+
+    /// Deepest directory level to scan, 1 for only the files directly inside the --scan directories; unlimited if omitted
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_depth: Option<u64>,
+
+    /// Only report groups with at least this many files
+    #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u64).range(2..))]
+    min_copies: u64,
+
+    /// Leave out files modified in the last N days, like work in progress
+    #[arg(long, value_name = "DAYS", value_parser = clap::value_parser!(u64).range(1..))]
+    skip_recent: Option<u64>,
+
+    /// Leave out read-only files, and system files on Windows
+    #[arg(long)]
+    skip_read_only: bool,
+
+    /// Also report zero-byte files, which are skipped by default
+    #[arg(long)]
+    include_empty: bool,
+
+    /// Verify hash matches byte by byte
+    #[arg(long)]
+    verify: bool,
+
+    /// Only report duplicates found in more than one of the --scan directories
+    #[arg(long)]
+    compare: bool,
+
+    /// Only report duplicates that also share the same file name
+    #[arg(long)]
+    same_name: bool,
+
+    /// Whether names differing only by case are different for --name, --name-regex and --same-name [default: false on Windows and macOS, true elsewhere]
+    #[arg(long, value_name = "BOOL")]
+    case_sensitive_names: Option<bool>,
+
+    /// Compare UTF-8 text files with a known extension in normalized form; binary files are always compared byte for byte
+    #[arg(long, value_enum, value_name = "MODE")]
+    normalize_text: Option<TextNormalization>,
+
+    /// Reuse the hashes of unchanged files from previous scans (shared with the GUI), and tell how many were reused
+    #[arg(long)]
+    cache: bool,
+
+    /// Append a log of the scan to this file
+    #[arg(long, value_name = "FILE")]
+    log: Option<PathBuf>,
+
+    /// How symbolic links are treated; on Windows junctions count as links
+    #[arg(long, value_enum, default_value_t = SymlinkPolicy::Skip)]
+    symlinks: SymlinkPolicy,
+
+    /// How Windows reparse points other than links are treated, like OneDrive placeholders
+    #[arg(long, value_enum, default_value_t = ReparsePolicy::Skip)]
+    reparse_points: ReparsePolicy,
+
+    /// Also scan the files inside zip archives, reported as "archive.zip::file"
+    #[arg(long)]
+    archives: bool,
+
+    /// Number of worker threads, 0 for one per core
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    threads: usize,
+
+    /// Compare the results with an earlier CSV or JSON export and list the new, changed and resolved groups
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+}
+
+// Characters of the command-line progress line, which is overwritten in place
+const PROGRESS_LINE_WIDTH: usize = 79;
+
+// Function to print the progress of a command-line scan on one line of the terminal
+fn print_progress(update: ProgressUpdate) {
+    let line = if !update.discovered {
+        format!("Discovering... {} files found", format_count(update.files_found))
+    } else {
+        format!("{} {:.0}% ({} of {} files)", update.phase.label(), update.fraction * 100.0, format_count(update.processed.min(update.total)), format_count(update.total))
+    };
+    let room = PROGRESS_LINE_WIDTH.saturating_sub(line.chars().count() + 1);
+    let line = if update.current_file.is_empty() || room < 10 { line } else { format!("{} {}", line, truncate_path(&update.current_file, room)) };
+    eprint!("\r{:width$}", line, width = PROGRESS_LINE_WIDTH); // Padded to hide the end of a longer previous line
+    let _ = io::stderr().flush();
+}
+
+// Function to run a scan from the command line, returning the process exit code
+fn run_cli(cli: Cli) -> i32 {
+    if let Err(e) = set_log_file(cli.log.as_deref()) {
+        eprintln!("Failed to open the log file: {}", e);
+        return 1;
+    }
+    let case_sensitive = cli.case_sensitive_names.unwrap_or(CASE_SENSITIVE_NAMES);
+    let name_pattern = match (&cli.name, &cli.name_regex) {
+        (Some(glob), _) => parse_name_pattern(glob, PatternKind::Glob, case_sensitive),
+        (None, Some(regex)) => parse_name_pattern(regex, PatternKind::Regex, case_sensitive),
+        (None, None) => Ok(None),
+    };
+    let name_pattern = match name_pattern {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            eprintln!("Invalid file name pattern: {}", e);
+            return 1;
+        }
+    };
+    let file_list = match cli.files.as_deref() {
+        Some(path) if path == Path::new("-") => io::read_to_string(io::stdin()).map(|text| Some(parse_file_list(&text))),
+        Some(path) => fs::read_to_string(path).map(|text| Some(parse_file_list(&text))),
+        None => Ok(None),
+    };
+    let file_list = match file_list {
+        Ok(list) => list,
+        Err(e) => {
+            eprintln!("Failed to read the file list: {}", e);
+            return 1;
+        }
+    };
+    let filter = ScanFilter {
+        include_extensions: parse_extensions(cli.include.as_deref().unwrap_or_default()),
+        exclude_extensions: parse_extensions(cli.exclude.as_deref().unwrap_or_default()),
+        min_size: cli.min_size.max(u64::from(!cli.include_empty)), // Skipping empty files means at least 1 byte
+        symlinks: cli.symlinks,
+        reparse_points: cli.reparse_points,
+        skip_protected: cli.skip_read_only,
+        skip_recent: cli.skip_recent.map(days),
+        name_pattern,
+        excluded_dirs: cli.exclude_dir,
+        max_depth: cli.max_depth.map(|depth| depth as usize),
+        archives: cli.archives,
+        file_list,
+        ..ScanFilter::default()
+    };
+    // Read before scanning, so a wrong file doesn't waste a whole scan
+    let baseline = match cli.baseline.as_deref().map(read_export).transpose() {
+        Ok(baseline) => baseline,
+        Err(e) => {
+            eprintln!("Failed to import the baseline: {}", e);
+            return 1;
+        }
+    };
+    let scanner = DupScanner::new(cli.scan)
+        .algorithm(cli.algo)
+        .filter(filter)
+        .threads(cli.threads)
+        .verify(cli.verify) // Rule out hash collisions
+        .same_name(cli.same_name) // Only copies with the same name
+        .case_sensitive_names(case_sensitive)
+        .normalize_text(cli.normalize_text) // Text files differing only in formatting match
+        .compare(cli.compare) // Only duplicates across directories
+        .cache(if cli.cache { hash_cache_path() } else { None });
+    // Keep a progress line updated on the terminal, but never clutter redirected output
+    let show_progress = io::stderr().is_terminal();
+    let scanner = if show_progress { scanner.on_progress(print_progress) } else { scanner };
+    let started = Instant::now(); // Timing the scan for the summary
+    let report = scanner.scan();
+    if show_progress {
+        eprint!("\r{:width$}\r", "", width = PROGRESS_LINE_WIDTH); // Clear the progress line
+    }
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Scan failed: {}", e);
+            return 1;
+        }
+    };
+    for (path, e) in &report.errors {
+        eprintln!("Skipped {}: {}", path.display(), e); // Report files that couldn't be read
+    }
+    let found: HashMap<String, DuplicateGroup> = report.groups
+        .into_iter()
+        .filter(|group| group.files.len() as u64 >= cli.min_copies) // Only the most redundant content, if asked
+        .map(|group| (group.hash.clone(), group))
+        .collect();
+    if let (Some(baseline), Some(path)) = (&baseline, &cli.baseline) {
+        let comparison = compare_results(&found, baseline);
+        eprintln!("Compared with {}: {}", path.display(), comparison.summary());
+        if comparison.disjoint() {
+            eprintln!("Warning: no group shares a hash with the baseline, was it made with another --algo?");
+        }
+        for group in &comparison.new {
+            eprintln!("New: {} ({} files)", group.hash, group.files.len());
+        }
+        for group in &comparison.changed {
+            eprintln!("Changed: {} ({} files)", group.hash, group.files.len());
+        }
+        for group in &comparison.resolved {
+            eprintln!("Resolved: {} ({} files)", group.hash, group.files.len());
+        }
+    }
+
+    // Write the results where requested
+    let groups = export_groups(&found);
+    let result = match &cli.output {
+        Some(path) => {
+            let is = |extension: &str| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension));
+            let format = if is("csv") { ExportFormat::Csv } else if is("html") || is("htm") { ExportFormat::Html } else { ExportFormat::Json };
+            write_export(path, format, &groups, cli.thumbnails)
+        }
+        None => write_groups(io::stdout().lock(), ExportFormat::Json, &groups, false),
+    };
+    match result {
+        Ok(()) => {
+            eprintln!("{}", scan_summary(&scanner.progress(), started.elapsed(), groups.len()));
+            if let Some(stats) = report.cache {
+                eprintln!("{}", cache_summary(stats));
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to write results: {}", e);
+            1
+        }
+    }
+}
+
+// Function to reuse the parent console on Windows, where the GUI subsystem has none of its own
+#[cfg(windows)]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS); // Best effort, output is simply lost without a parent console
+    }
+}
+
+// Entry point for the application
+fn main() -> Result<(), eframe::Error> {
+    init_logging();
+
+    // Run headless when any command-line arguments are given
+    if std::env::args_os().len() > 1 {
+        #[cfg(windows)]
+        attach_console();
+        std::process::exit(run_cli(Cli::parse()));
+    }
+
+    let native_options = eframe::NativeOptions::default(); // Default native options for the app
+    eframe::run_native(
+        APP_NAME, // Window title
+        native_options, // Native options
+        Box::new(|cc| Ok(Box::new(DuplicateFinderApp::new(cc)))), // Create the app instance with saved settings
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Function to build a group of files that were never on disk
+    fn group(hash: &str, content: &str, paths: &[&str]) -> DuplicateGroup {
+        let files = paths.iter().map(|path| FileEntry { path: path.to_string(), size: 10, modified: None, root: 0 }).collect();
+        DuplicateGroup { content: content.to_string(), ..DuplicateGroup::new(hash.to_string(), 10, files) }
+    }
+
+    #[test]
+    fn emptied_contents_tells_similar_images_apart() {
+        let mut first = group("dhash:00000000000000ff", "dhash:00000000000000ff", &["/a/1.jpg", "/a/2.jpg"]);
+        first.distance = Some(2);
+        let mut second = group("dhash:ff00000000000000", "dhash:ff00000000000000", &["/b/1.jpg", "/b/2.jpg"]);
+        second.distance = Some(1);
+        let duplicates = HashMap::from([(first.hash.clone(), first), (second.hash.clone(), second)]);
+
+        // The second group keeping its copies doesn't save the first one
+        let emptied = emptied_contents(&duplicates, &["/a/1.jpg".to_string(), "/a/2.jpg".to_string(), "/b/1.jpg".to_string()]);
+        assert_eq!(emptied.len(), 1);
+        assert_eq!(emptied[0].0, "dhash:00000000000000ff");
+    }
+
+    #[test]
+    fn emptied_contents_joins_groups_split_by_name() {
+        let photos = group("1f3a:photo.jpg", "1f3a", &["/a/photo.jpg", "/b/photo.jpg"]);
+        let copies = group("1f3a:copy.jpg", "1f3a", &["/a/copy.jpg", "/b/copy.jpg"]);
+        let duplicates = HashMap::from([(photos.hash.clone(), photos), (copies.hash.clone(), copies)]);
+
+        // Every photo.jpg goes, but the copies still hold the content
+        assert!(emptied_contents(&duplicates, &["/a/photo.jpg".to_string(), "/b/photo.jpg".to_string()]).is_empty());
+        let everything = emptied_contents(&duplicates, &["/a".to_string(), "/b".to_string()]);
+        assert_eq!(everything.len(), 1);
+        assert_eq!(everything[0].1.len(), 4);
+    }
+
+    #[test]
+    fn copied_file_keeps_its_modification_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        fs::write(&from, b"contents").unwrap();
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        File::options().write(true).open(&from).unwrap().set_modified(old).unwrap();
+
+        copy_file(&from, &to).unwrap();
+        assert_eq!(fs::metadata(&to).unwrap().modified().unwrap(), old);
+        assert_eq!(fs::read(&to).unwrap(), b"contents");
+    }
+
+    // Function to build the entry a scan would list for a file as it is right now
+    fn listed(path: &Path) -> FileEntry {
+        let metadata = fs::metadata(path).unwrap();
+        FileEntry { path: path.display().to_string(), size: metadata.len(), modified: metadata.modified().ok(), root: 0 }
+    }
+
+    #[test]
+    fn file_moved_back_is_not_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, moved) = (dir.path().join("a.txt"), dir.path().join("elsewhere.txt"));
+        fs::write(&path, b"contents").unwrap();
+        let file = listed(&path);
+        std::thread::sleep(Duration::from_millis(20)); // So a copy made now would get a different time
+
+        // A move to another drive and its undo, each copying the file and removing the original
+        copy_file(&path, &moved).unwrap();
+        fs::remove_file(&path).unwrap();
+        copy_file(&moved, &path).unwrap();
+        fs::remove_file(&moved).unwrap();
+        assert_eq!(stale_reason(&file.path, Some(&file)), None);
+    }
+
+    #[test]
+    fn files_changed_since_the_scan_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["a.txt", "b.txt", "c.txt"].iter().map(|name| dir.path().join(name)).collect();
+        for path in &paths {
+            fs::write(path, b"contents").unwrap();
+        }
+        let files: Vec<FileEntry> = paths.iter().map(|path| listed(path)).collect();
+        let mut app = DuplicateFinderApp::default();
+        app.duplicates.insert("h".to_string(), DuplicateGroup::new("h".to_string(), 8, files.clone()));
+        fs::write(&paths[1], b"edited contents").unwrap(); // Changed by another program after the scan
+        fs::remove_file(&paths[2]).unwrap();
+        assert_eq!(stale_reason(&files[1].path, Some(&files[1])).as_deref(), Some("modified since the scan"));
+        assert_eq!(stale_reason(&files[2].path, Some(&files[2])).as_deref(), Some("no longer exists"));
+
+        let targets: Vec<String> = files.iter().map(|file| file.path.clone()).collect();
+        let (valid, stale) = app.stale_targets(&targets);
+        assert_eq!(valid, [files[0].path.clone()]);
+        assert_eq!(stale.len(), 2);
+        assert_eq!(app.duplicates["h"].files.len(), 3); // Only checked, nothing pruned yet
+        assert_eq!(app.drop_stale(&stale).len(), 2);
+        assert!(app.duplicates.is_empty()); // A single file left is no longer a duplicate
+    }
+
+    #[test]
+    fn failed_search_returns_to_idle() {
+        let mut app = DuplicateFinderApp { scan_state: Arc::new(SharedScanState::new(ScanState::Scanning)), ..DuplicateFinderApp::default() };
+        let (sender, receiver) = mpsc::channel();
+        app.scan_results = Some(receiver);
+        let files = vec![FileEntry { path: "/a/1.txt".to_string(), size: 4, modified: None, root: 0 }, FileEntry { path: "/a/2.txt".to_string(), size: 4, modified: None, root: 0 }];
+        sender.send(ScanMessage::Found("h".to_string(), 4, files)).unwrap(); // Shown while the search ran
+
+        // What the search thread sends once its directory is gone
+        assert!(app.scan_state.finish());
+        sender.send(ScanMessage::Failed("The scanned directory /a is no longer available".to_string())).unwrap();
+        app.receive_scan_messages();
+
+        assert!(!app.scan_state.is_scanning());
+        assert!(!app.scan_state.is_busy()); // A new search can start
+        assert_eq!(app.scan_state.get(), ScanState::Idle);
+        assert!(app.scan_results.is_none());
+        assert!(app.duplicates.is_empty()); // Partial results can't be trusted
+        assert!(app.error_message.as_deref().is_some_and(|message| message.contains("no longer available")));
+    }
+
+    #[test]
+    fn safe_mode_keeps_the_last_unchanged_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.txt"), dir.path().join("b.txt"));
+        fs::write(&a, b"contents").unwrap();
+        fs::write(&b, b"contents").unwrap();
+        let mut app = DuplicateFinderApp { safe_mode: true, ..DuplicateFinderApp::default() };
+        app.duplicates.insert("h".to_string(), DuplicateGroup::new("h".to_string(), 8, vec![listed(&a), listed(&b)]));
+        fs::write(&a, b"edited contents").unwrap(); // Changed by another program, so a is skipped and b is the last copy
+
+        let selected = [a.display().to_string(), b.display().to_string()];
+        app.delete_files(&selected, true);
+        assert!(b.exists());
+        assert!(app.error_message.as_deref().is_some_and(|message| message.contains("Safe mode refused the deletion")));
+        assert_eq!(app.duplicates["h"].files.len(), 2); // Left as it was, nothing was done
+    }
 }